@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rusqlite::Connection;
+
+use crate::journal;
+use crate::session::{self, say};
+
+// How often a background job checks whether it has been told to stop
+const STOP_CHECK: Duration = Duration::from_millis(100);
+// How long a job's connection and the REPL's wait for each other before giving up on a write
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Read by every job while it runs, written by whoever needs them all to keep still
+static PAUSE: RwLock<()> = RwLock::new(());
+
+// A job run on its own thread every `interval`, on the wall clock, until it's dropped
+pub struct Background {
+    pub interval: Duration,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Background {
+    pub fn start(interval: Duration, mut job: impl FnMut() + Send + 'static) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let stop = Arc::clone(&stop);
+            move || {
+                let mut last_run = Instant::now();
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(STOP_CHECK);
+                    if last_run.elapsed() < interval {
+                        continue;
+                    }
+                    last_run = Instant::now();
+                    // A paused job sits this run out
+                    if let Ok(_running) = PAUSE.try_read() {
+                        job();
+                    }
+                }
+            }
+        });
+
+        Self {
+            interval,
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+// Dropping the job stops it and waits for it to put down whatever it was holding
+impl Drop for Background {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Keeps every job still until the guard is dropped, once any run already under way is done.
+// A mini-game holds it while it has the terminal, so nothing gets printed over its screen and
+// the table it's played for only changes by the game's hand.
+pub fn pause() -> RwLockWriteGuard<'static, ()> {
+    PAUSE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Jobs run on the wall clock, so no two runs see one strike at the same moment, and a replay
+// could never match what was recorded with one running. They sit out recorded and replayed
+// sessions.
+pub fn sits_out_sessions() -> bool {
+    session::is_active()
+}
+
+// Whether a job on a connection of its own can get at the database at `path`. Its own
+// connection to a database that only lives in memory would get a brand new, empty one.
+pub fn can_reach(path: &str) -> bool {
+    path != crate::IN_MEMORY_DB_NAME
+}
+
+// Tells whoever's at the prompt what a job got up to, on a line of its own, and journals it.
+// Sessions are kept per thread, so none of this ends up in a --record'ed one.
+pub fn report(conn: &Connection, job: &str, table_name: &str, outcome: &str, message: &str) {
+    say!("\n{}", message);
+    let _ = journal::record(conn, &format!("({})", job), job, table_name, 1, outcome);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    #[test]
+    fn paused_jobs_sit_their_runs_out() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let _job = Background::start(Duration::ZERO, {
+            let runs = Arc::clone(&runs);
+            move || {
+                runs.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        let paused = pause();
+        let before = runs.load(Ordering::Relaxed);
+        thread::sleep(STOP_CHECK * 4);
+        assert_eq!(runs.load(Ordering::Relaxed), before);
+
+        drop(paused);
+        thread::sleep(STOP_CHECK * 4);
+        assert!(runs.load(Ordering::Relaxed) > before);
+    }
+}
@@ -0,0 +1,332 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::rows::{self, RowData};
+
+// The shadow table: a checksum of every row as it was last written on purpose, along with a
+// copy of the row to repair it from
+pub const CHECKSUM_TABLE: &str = "_wacky_checksums";
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// A row whose checksum doesn't add up anymore
+pub struct CorruptRow {
+    pub table_name: String,
+    pub rowid: i64,
+    pub found: RowData,
+    pub expected: RowData,
+}
+
+impl CorruptRow {
+    // The columns that rotted, with what they should have been and what they are now
+    pub fn differences(&self) -> Vec<(&str, &serde_json::Value, &serde_json::Value)> {
+        self.expected
+            .iter()
+            .filter_map(|(column, expected)| {
+                let found = self.found.get(column)?;
+                (found != expected).then_some((column.as_str(), expected, found))
+            })
+            .collect()
+    }
+}
+
+pub fn ensure_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                table_name TEXT NOT NULL,
+                row_id INTEGER NOT NULL,
+                checksum TEXT NOT NULL,
+                row_data TEXT NOT NULL,
+                PRIMARY KEY (table_name, row_id)
+            )",
+            CHECKSUM_TABLE
+        ),
+        [],
+    )?;
+
+    Ok(())
+}
+
+// FNV-1a over the row as JSON. Not cryptographic, but bit rot isn't out to fool anyone.
+pub fn checksum(data: &RowData) -> Result<String, serde_json::Error> {
+    let hash = serde_json::to_string(data)?
+        .bytes()
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        });
+
+    Ok(format!("{:016x}", hash))
+}
+
+// Trusts the rows as they are now. Called after every legitimate write.
+pub fn checksum_rows(
+    conn: &Connection,
+    table_name: &str,
+    where_str: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for (rowid, data) in rows::fetch_rows(conn, table_name, where_str)? {
+        conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} (table_name, row_id, checksum, row_data)
+                 VALUES (?1, ?2, ?3, ?4)",
+                CHECKSUM_TABLE
+            ),
+            params![
+                table_name,
+                rowid,
+                checksum(&data)?,
+                serde_json::to_string(&data)?
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn checksum_row(
+    conn: &Connection,
+    table_name: &str,
+    rowid: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    checksum_rows(conn, table_name, &format!("rowid = {}", rowid))
+}
+
+// Called after rows are deleted, so a later row that reuses a rowid isn't held to the checksum
+// of the row that had it before
+pub fn forget_rows(conn: &Connection, table_name: &str, rowids: &[i64]) -> rusqlite::Result<()> {
+    for rowid in rowids {
+        conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE table_name = ?1 AND row_id = ?2",
+                CHECKSUM_TABLE
+            ),
+            params![table_name, rowid],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn forget_table(conn: &Connection, table_name: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!("DELETE FROM {} WHERE table_name = ?1", CHECKSUM_TABLE),
+        [table_name],
+    )?;
+
+    Ok(())
+}
+
+// Keeps the checksums of a table schema drift renamed
+pub fn rename_table(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "UPDATE {} SET table_name = ?2 WHERE table_name = ?1",
+            CHECKSUM_TABLE
+        ),
+        [from, to],
+    )?;
+
+    Ok(())
+}
+
+// The column names are part of what's checksummed, so every checksum of the table is redone
+// from its stored copy, not from the rows as they are now
+pub fn rename_column(
+    conn: &Connection,
+    table_name: &str,
+    from: &str,
+    to: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT row_id, row_data FROM {} WHERE table_name = ?1",
+        CHECKSUM_TABLE
+    ))?;
+    let stored = stmt
+        .query_map([table_name], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<(i64, String)>, _>>()?;
+
+    for (rowid, row_data) in stored {
+        let mut data: RowData = serde_json::from_str(&row_data)?;
+        rows::rename_key(&mut data, from, to);
+        conn.execute(
+            &format!(
+                "UPDATE {} SET checksum = ?1, row_data = ?2 WHERE table_name = ?3 AND row_id = ?4",
+                CHECKSUM_TABLE
+            ),
+            params![
+                checksum(&data)?,
+                serde_json::to_string(&data)?,
+                table_name,
+                rowid
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+// Quietly corrupts up to `count` values in the table: a flipped character in TEXT, an
+// off-by-one INTEGER. The checksums are left alone, so SCRUB can catch it later. Primary
+// keys are spared: rotting an INTEGER PRIMARY KEY would move the row to another rowid, out
+// from under its checksum. Returns how many values rotted.
+pub fn rot(
+    conn: &Connection,
+    rng: &mut StdRng,
+    table_name: &str,
+    count: u32,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let keys: Vec<String> = rows::column_info(conn, table_name)?
+        .into_iter()
+        .filter(|column| column.primary_key)
+        .map(|column| column.name)
+        .collect();
+
+    let mut rotted = 0;
+    for _ in 0..count {
+        let rows = rows::fetch_rows(conn, table_name, "1 = 1")?;
+        let candidates: Vec<(i64, &String, &serde_json::Value)> = rows
+            .iter()
+            .flat_map(|(rowid, data)| {
+                data.iter()
+                    .map(move |(column, value)| (*rowid, column, value))
+            })
+            .filter(|(_, column, _)| !keys.contains(column))
+            .filter(|(_, _, value)| value.is_i64() || value.as_str().is_some_and(|s| !s.is_empty()))
+            .collect();
+        let Some((rowid, column, value)) = candidates.choose(rng) else {
+            break;
+        };
+
+        let rotten = match value {
+            serde_json::Value::String(text) => flip_character(rng, text).into(),
+            _ => {
+                let number = value.as_i64().unwrap_or_default();
+                let nudge = if rng.gen_bool(0.5) { 1 } else { -1 };
+                number.wrapping_add(nudge).into()
+            }
+        };
+
+        // A UNIQUE or CHECK constraint can stop the rot, which is fine by everyone
+        let changed = conn
+            .execute(
+                &format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", table_name, column),
+                params![rows::from_json(&rotten), rowid],
+            )
+            .unwrap_or(0);
+        rotted += changed;
+    }
+
+    Ok(rotted)
+}
+
+fn flip_character(rng: &mut StdRng, text: &str) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+    let i = rng.gen_range(0..chars.len());
+    let original = chars[i];
+    // Pick from the other 25 letters so the flip always shows
+    let mut flipped = rng.gen_range(b'a'..=b'y') as char;
+    if original.is_ascii_lowercase() && flipped >= original {
+        flipped = (flipped as u8 + 1) as char;
+    }
+    chars[i] = flipped;
+    chars.into_iter().collect()
+}
+
+// Checks every row that has a checksum. Checksums left behind by rows deleted without
+// forget_rows are pruned on the way, whatever deleted them.
+pub fn scrub(
+    conn: &Connection,
+    table_name: Option<&str>,
+) -> Result<Vec<CorruptRow>, Box<dyn std::error::Error>> {
+    let tables = match table_name {
+        Some(table_name) => vec![table_name.to_string()],
+        None => rows::user_tables(conn)?,
+    };
+
+    let mut corrupt = Vec::new();
+    for table_name in tables {
+        conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE table_name = ?1 AND row_id NOT IN (SELECT rowid FROM {})",
+                CHECKSUM_TABLE, table_name
+            ),
+            [&table_name],
+        )?;
+
+        for (rowid, found) in rows::fetch_rows(conn, &table_name, "1 = 1")? {
+            let stored: Option<(String, String)> = conn
+                .query_row(
+                    &format!(
+                        "SELECT checksum, row_data FROM {} WHERE table_name = ?1 AND row_id = ?2",
+                        CHECKSUM_TABLE
+                    ),
+                    params![table_name, rowid],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            let Some((expected_checksum, row_data)) = stored else {
+                continue;
+            };
+
+            if checksum(&found)? != expected_checksum {
+                corrupt.push(CorruptRow {
+                    table_name: table_name.clone(),
+                    rowid,
+                    found,
+                    expected: serde_json::from_str(&row_data)?,
+                });
+            }
+        }
+    }
+
+    Ok(corrupt)
+}
+
+// Writes the row back the way its checksum remembers it
+pub fn repair(conn: &Connection, row: &CorruptRow) -> Result<(), Box<dyn std::error::Error>> {
+    for (column, expected, _) in row.differences() {
+        conn.execute(
+            &format!(
+                "UPDATE {} SET {} = ?1 WHERE rowid = ?2",
+                row.table_name, column
+            ),
+            params![rows::from_json(expected), row.rowid],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn rot_leaves_primary_keys_alone_so_scrub_can_find_it() {
+        for seed in 0..40 {
+            let conn = Connection::open_in_memory().unwrap();
+            ensure_table(&conn).unwrap();
+            conn.execute(
+                "CREATE TABLE pets (id INTEGER PRIMARY KEY, age INTEGER)",
+                [],
+            )
+            .unwrap();
+            conn.execute("INSERT INTO pets VALUES (1, 3), (2, 5), (3, 7)", [])
+                .unwrap();
+            checksum_rows(&conn, "pets", "1 = 1").unwrap();
+
+            let mut rng = StdRng::seed_from_u64(seed);
+            let rotted = rot(&conn, &mut rng, "pets", 1).unwrap();
+            let corrupt = scrub(&conn, Some("pets")).unwrap();
+
+            assert_eq!(rotted, 1, "seed {}", seed);
+            assert_eq!(corrupt.len(), 1, "seed {}", seed);
+            assert_eq!(corrupt[0].differences()[0].0, "age");
+        }
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+use rand::{rngs::StdRng, Rng};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{Assignment, Statement};
+
+use crate::difficulty::Difficulty;
+use crate::game::GameRegistry;
+use crate::results::ResultSet;
+use crate::session::say;
+use crate::snapshot::{self, Changes};
+use crate::ColumnDefinition;
+
+// The weight of this name is the chance of no effect at all
+pub const PROCEED_AS_NORMAL: &str = "proceed_as_normal";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    Create,
+    Insert,
+    Select,
+    Update,
+    Drop,
+}
+
+impl StatementKind {
+    pub const ALL: [StatementKind; 5] = [
+        StatementKind::Create,
+        StatementKind::Insert,
+        StatementKind::Select,
+        StatementKind::Update,
+        StatementKind::Drop,
+    ];
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StatementKind::Create => "CREATE",
+            StatementKind::Insert => "INSERT",
+            StatementKind::Select => "SELECT",
+            StatementKind::Update => "UPDATE",
+            StatementKind::Drop => "DROP",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for StatementKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StatementKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("'{}' is not a statement WackyDB gets wacky with", s))
+    }
+}
+
+// A statement that is about to hit the database. Effects may look at it and change it before
+// it runs. Fields that don't apply to the statement's kind are left empty.
+pub struct PendingOperation {
+    // The statement as the user typed it (give or take some whitespace)
+    pub sql: String,
+    // The statement as it was parsed, before chaos or schema drift got their hands on the
+    // operation. None for operations that didn't come from the parser.
+    pub statement: Option<Statement>,
+    pub kind: StatementKind,
+    pub table_name: String,
+    pub columns: Vec<ColumnDefinition>,
+    pub values: Vec<String>,
+    // Which of the operations planned from one statement this is, counting from 0: the row of
+    // a multi-row INSERT, or the table of a DROP naming several. Time passes once a statement,
+    // on its first part. An INSERT gets one roll for chaos too, on its first row, at the odds
+    // of all its rows together.
+    pub statement_part: usize,
+    pub projection: String,
+    pub assignments: Vec<Assignment>,
+    pub where_clauses: Vec<Assignment>,
+    // The effect that gets to rewrite a SELECT's rows before they're shown
+    pub lie: Option<String>,
+}
+
+impl PendingOperation {
+    pub fn new(kind: StatementKind, table_name: &str) -> Self {
+        Self {
+            sql: String::new(),
+            statement: None,
+            kind,
+            table_name: table_name.to_string(),
+            columns: Vec::new(),
+            values: Vec::new(),
+            statement_part: 0,
+            projection: String::new(),
+            assignments: Vec::new(),
+            where_clauses: Vec::new(),
+            lie: None,
+        }
+    }
+}
+
+pub enum EffectOutcome {
+    // Run the (possibly altered) operation
+    Proceed,
+    // Refuse to run the operation, telling the user why
+    Abort(String),
+}
+
+pub struct ChaosContext<'a> {
+    pub conn: &'a Connection,
+    pub rng: &'a mut StdRng,
+    // Effects should hit harder on harder difficulties
+    pub difficulty: Difficulty,
+    // What there is to play when it's gaming time
+    pub games: &'a GameRegistry,
+    // Who's at the keyboard, for the leaderboard
+    pub player: &'a str,
+    // Effects that touch rows count them here, for the journal
+    pub rows_affected: usize,
+    // What the effect did under `undoable`, for UNDO CHAOS to take back
+    pub changes: Changes,
+}
+
+impl ChaosContext<'_> {
+    // Runs the part of an effect that does damage to a table, writing down what it did so
+    // UNDO CHAOS can take it back. Only rows this connection writes during the damage are
+    // written down, so a game played beforehand, a write to any other table or whatever the
+    // background jobs get up to isn't mistaken for chaos.
+    pub fn undoable<T>(
+        &mut self,
+        table_name: &str,
+        damage: impl FnOnce(&mut Self) -> Result<T, Box<dyn std::error::Error>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let before = snapshot::take(self.conn, table_name)
+            .map_err(|e| say!("Couldn't take a snapshot, this one is for keeps: {}", e))
+            .ok();
+        let result = damage(self);
+        if let Some(before) = before {
+            match before.finish(self.conn) {
+                Ok(changes) => self.changes.append(changes),
+                Err(e) => say!("Couldn't take a snapshot, this one is for keeps: {}", e),
+            }
+        }
+
+        result
+    }
+}
+
+pub trait ChaosEffect {
+    // The name weighted in chaos profiles, e.g. "database_on_fire"
+    fn name(&self) -> &str;
+
+    fn apply(
+        &self,
+        ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>>;
+
+    // Called with the real rows of a SELECT this effect signed up to lie about (see
+    // `PendingOperation::lie`). Returns what it did to them, or None if it couldn't lie.
+    fn distort(&self, _ctx: &mut ChaosContext, _result: &mut ResultSet) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct EffectRegistry {
+    effects: BTreeMap<String, Box<dyn ChaosEffect>>,
+}
+
+impl EffectRegistry {
+    // Registering an effect with a name that is already taken replaces the old one
+    pub fn register(&mut self, effect: Box<dyn ChaosEffect>) {
+        self.effects.insert(effect.name().to_string(), effect);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn ChaosEffect> {
+        self.effects.get(name).map(|effect| effect.as_ref())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.effects.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.effects.keys().map(|name| name.as_str())
+    }
+}
+
+// Decides which effect, if any, gets to hit an operation. WackyDB rolls the chaos profile,
+// tests plug in something they can count on.
+pub trait ChaosSource {
+    // The name of the effect to apply, or None to proceed as normal
+    fn pick(
+        &mut self,
+        profile: &ChaosProfile,
+        kind: StatementKind,
+        rng: &mut StdRng,
+        frequency: u32,
+    ) -> Option<String>;
+}
+
+// Rolls the chaos profile, the way WackyDB always has
+pub struct RollChaos;
+
+impl ChaosSource for RollChaos {
+    fn pick(
+        &mut self,
+        profile: &ChaosProfile,
+        kind: StatementKind,
+        rng: &mut StdRng,
+        frequency: u32,
+    ) -> Option<String> {
+        profile.roll(kind, rng, frequency).map(str::to_string)
+    }
+}
+
+// Chaos sources tests can count on
+#[cfg(test)]
+pub mod scripted {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    // Every statement proceeds as normal
+    pub struct AlwaysProceed;
+
+    impl ChaosSource for AlwaysProceed {
+        fn pick(
+            &mut self,
+            _: &ChaosProfile,
+            _: StatementKind,
+            _: &mut StdRng,
+            _: u32,
+        ) -> Option<String> {
+            None
+        }
+    }
+
+    // The same effect, every single time
+    pub struct AlwaysEffect(pub String);
+
+    impl ChaosSource for AlwaysEffect {
+        fn pick(
+            &mut self,
+            _: &ChaosProfile,
+            _: StatementKind,
+            _: &mut StdRng,
+            _: u32,
+        ) -> Option<String> {
+            Some(self.0.clone())
+        }
+    }
+
+    // Effects in the order given, None meaning proceed as normal. Once the script runs out,
+    // everything proceeds as normal.
+    pub struct ScriptedChaos(VecDeque<Option<String>>);
+
+    impl ScriptedChaos {
+        pub fn new<'a>(script: impl IntoIterator<Item = Option<&'a str>>) -> Self {
+            Self(
+                script
+                    .into_iter()
+                    .map(|name| name.map(str::to_string))
+                    .collect(),
+            )
+        }
+    }
+
+    impl ChaosSource for ScriptedChaos {
+        fn pick(
+            &mut self,
+            _: &ChaosProfile,
+            _: StatementKind,
+            _: &mut StdRng,
+            _: u32,
+        ) -> Option<String> {
+            self.0.pop_front().flatten()
+        }
+    }
+}
+
+pub type ChaosWeights = BTreeMap<String, u32>;
+
+// How likely each chaos effect is, per statement type. Weights are relative to each other,
+// so a statement whose weights are all zero always proceeds as normal.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ChaosProfile {
+    pub create: ChaosWeights,
+    pub insert: ChaosWeights,
+    pub select: ChaosWeights,
+    pub update: ChaosWeights,
+    pub drop: ChaosWeights,
+}
+
+impl Default for ChaosProfile {
+    fn default() -> Self {
+        // About a 3 in 10 chance of something wacky, split evenly over the effects, with
+        // schema drift a rarer sight
+        let weights = ChaosWeights::from([
+            (PROCEED_AS_NORMAL.to_string(), 70),
+            ("gaming_time".to_string(), 6),
+            ("database_on_fire".to_string(), 6),
+            ("data_in_trash".to_string(), 6),
+            ("nothing_happened".to_string(), 6),
+            ("bit_rot".to_string(), 6),
+            ("schema_drift".to_string(), 3),
+        ]);
+
+        // SELECTs can also be lied to
+        let mut select = weights.clone();
+        select.extend([
+            ("shuffle_rows".to_string(), 3),
+            ("swap_columns".to_string(), 3),
+            ("invent_rows".to_string(), 3),
+            ("hide_rows".to_string(), 3),
+        ]);
+
+        Self {
+            create: weights.clone(),
+            insert: weights.clone(),
+            select,
+            update: weights.clone(),
+            drop: weights,
+        }
+    }
+}
+
+impl ChaosProfile {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Every effect the profile gives a weight to has to be registered, or it'd be rolled
+    // without anything there to strike
+    pub fn check(&self, effects: &EffectRegistry) -> Result<(), String> {
+        let unknown: Vec<&str> = self
+            .effect_names()
+            .into_iter()
+            .filter(|name| *name != PROCEED_AS_NORMAL && !effects.contains(name))
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+
+        Err(format!(
+            "{} is not a kind of chaos I know",
+            unknown.join(", ")
+        ))
+    }
+
+    pub fn weights(&self, kind: StatementKind) -> &ChaosWeights {
+        match kind {
+            StatementKind::Create => &self.create,
+            StatementKind::Insert => &self.insert,
+            StatementKind::Select => &self.select,
+            StatementKind::Update => &self.update,
+            StatementKind::Drop => &self.drop,
+        }
+    }
+
+    pub fn set_weight(&mut self, kind: StatementKind, effect: &str, weight: u32) {
+        let weights = match kind {
+            StatementKind::Create => &mut self.create,
+            StatementKind::Insert => &mut self.insert,
+            StatementKind::Select => &mut self.select,
+            StatementKind::Update => &mut self.update,
+            StatementKind::Drop => &mut self.drop,
+        };
+        weights.insert(effect.to_string(), weight);
+    }
+
+    // Picks the name of an effect to apply, or None to proceed as normal. The frequency is a
+    // percentage every effect's weight is scaled by, leaving proceed_as_normal as it is.
+    pub fn roll<R: Rng>(&self, kind: StatementKind, rng: &mut R, frequency: u32) -> Option<&str> {
+        let weights = self.scaled_weights(kind, frequency);
+        let total: u64 = weights.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rng.gen_range(0..total);
+        for (name, weight) in weights {
+            if roll < weight {
+                return Some(name).filter(|name| *name != PROCEED_AS_NORMAL);
+            }
+            roll -= weight;
+        }
+
+        None
+    }
+
+    // The odds of `roll` picking any effect at all, between 0 and 1
+    pub fn chaos_chance(&self, kind: StatementKind, frequency: u32) -> f64 {
+        let weights = self.scaled_weights(kind, frequency);
+        let total: u64 = weights.iter().map(|(_, weight)| weight).sum();
+        let chaos: u64 = weights
+            .iter()
+            .filter(|(name, _)| *name != PROCEED_AS_NORMAL)
+            .map(|(_, weight)| weight)
+            .sum();
+        if total == 0 {
+            return 0.0;
+        }
+
+        chaos as f64 / total as f64
+    }
+
+    // Weights in hundredths, so scaling a small weight down doesn't round it away to nothing
+    fn scaled_weights(&self, kind: StatementKind, frequency: u32) -> Vec<(&str, u64)> {
+        self.weights(kind)
+            .iter()
+            .map(|(name, weight)| match name.as_str() {
+                PROCEED_AS_NORMAL => (name.as_str(), *weight as u64 * 100),
+                _ => (name.as_str(), *weight as u64 * frequency as u64),
+            })
+            .collect()
+    }
+
+    fn effect_names(&self) -> Vec<&str> {
+        let mut names: BTreeSet<&str> = BTreeSet::new();
+        for kind in StatementKind::ALL {
+            names.extend(self.weights(kind).keys().map(|name| name.as_str()));
+        }
+        names.remove(PROCEED_AS_NORMAL);
+
+        std::iter::once(PROCEED_AS_NORMAL).chain(names).collect()
+    }
+}
+
+impl fmt::Display for ChaosProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self.effect_names();
+
+        write!(f, "{:<10}", "")?;
+        for name in &names {
+            write!(f, "{:>19}", name)?;
+        }
+
+        for kind in StatementKind::ALL {
+            write!(f, "\n{:<10}", kind.to_string())?;
+            let weights = self.weights(kind);
+            for name in &names {
+                write!(f, "{:>19}", weights.get(*name).copied().unwrap_or(0))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scripted::ScriptedChaos;
+    use super::*;
+    use rand::SeedableRng;
+
+    fn only(effect: &str, weight: u32) -> ChaosProfile {
+        let mut profile = ChaosProfile::default();
+        for kind in StatementKind::ALL {
+            let weights = match kind {
+                StatementKind::Create => &mut profile.create,
+                StatementKind::Insert => &mut profile.insert,
+                StatementKind::Select => &mut profile.select,
+                StatementKind::Update => &mut profile.update,
+                StatementKind::Drop => &mut profile.drop,
+            };
+            weights.clear();
+        }
+        profile.set_weight(StatementKind::Insert, PROCEED_AS_NORMAL, 100 - weight);
+        profile.set_weight(StatementKind::Insert, effect, weight);
+        profile
+    }
+
+    #[test]
+    fn statement_kinds_parse_case_insensitively() {
+        assert_eq!("select".parse(), Ok(StatementKind::Select));
+        assert_eq!("DROP".parse(), Ok(StatementKind::Drop));
+        assert!("DELETE".parse::<StatementKind>().is_err());
+    }
+
+    #[test]
+    fn zero_frequency_always_proceeds() {
+        let profile = only("database_on_fire", 100);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..100 {
+            assert_eq!(profile.roll(StatementKind::Insert, &mut rng, 0), None);
+        }
+        assert_eq!(profile.chaos_chance(StatementKind::Insert, 0), 0.0);
+    }
+
+    #[test]
+    fn frequency_scales_the_odds_of_chaos() {
+        let profile = only("database_on_fire", 50);
+
+        assert_eq!(profile.chaos_chance(StatementKind::Insert, 100), 0.5);
+        assert_eq!(profile.chaos_chance(StatementKind::Insert, 300), 0.75);
+        // Statements without weights never see chaos
+        assert_eq!(profile.chaos_chance(StatementKind::Drop, 100), 0.0);
+    }
+
+    #[test]
+    fn small_weights_survive_a_low_frequency() {
+        let profile = only("database_on_fire", 1);
+
+        assert!(profile.chaos_chance(StatementKind::Insert, 25) > 0.0);
+    }
+
+    #[test]
+    fn rolls_are_repeatable_from_a_seed() {
+        let profile = ChaosProfile::default();
+        let roll = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..20)
+                .map(|_| profile.roll(StatementKind::Select, &mut rng, 100))
+                .map(|name| name.map(str::to_string))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(roll(7), roll(7));
+    }
+
+    #[test]
+    fn scripted_chaos_runs_out_into_proceeding() {
+        let profile = ChaosProfile::default();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut source = ScriptedChaos::new([Some("bit_rot"), None, Some("hide_rows")]);
+        let mut pick = || source.pick(&profile, StatementKind::Select, &mut rng, 100);
+
+        assert_eq!(pick().as_deref(), Some("bit_rot"));
+        assert_eq!(pick(), None);
+        assert_eq!(pick().as_deref(), Some("hide_rows"));
+        assert_eq!(pick(), None);
+    }
+
+    #[test]
+    fn profiles_only_name_registered_effects() {
+        let mut effects = EffectRegistry::default();
+        crate::effects::register_builtin_effects(&mut effects);
+
+        assert_eq!(ChaosProfile::default().check(&effects), Ok(()));
+        assert_eq!(
+            only("database_on_flames", 10).check(&effects),
+            Err("database_on_flames is not a kind of chaos I know".to_string())
+        );
+    }
+}
@@ -0,0 +1,784 @@
+use std::time::Duration;
+
+use rand::Rng;
+use sqlparser::dialect::GenericDialect;
+
+use crate::chaos::{ChaosProfile, StatementKind, PROCEED_AS_NORMAL};
+use crate::difficulty::Difficulty;
+use crate::latency::{LatencyConfig, LatencyDistribution, MAX_PER_ROW_MS};
+use crate::session::say;
+use crate::{background, gremlin};
+use crate::{bitrot, decay, drift, fire, journal, leaderboard, risk, trash, WackyDB};
+
+// Wacky commands that aren't SQL. These are checked before the input reaches the parser.
+pub enum Command {
+    Seed(Option<u64>),
+    ShowChaos,
+    LoadChaos(String),
+    SaveChaos(String),
+    SetChaos(StatementKind, String, u32),
+    ResetChaos,
+    ShowTrash,
+    Restore(Option<i64>),
+    EmptyTrash,
+    Extinguish(Option<String>),
+    ShowLatency,
+    SetLatency(LatencyDistribution),
+    SetRowLatency(f64),
+    ShowDecay,
+    SetDecayRate(u32),
+    DecaySkip(String, Option<String>),
+    DecayUnskip(String, Option<String>),
+    ShowDecayLog,
+    ShowJournal(Option<String>),
+    ShowJournalSummary,
+    SetDifficulty(Option<Difficulty>),
+    UndoChaos,
+    Scrub {
+        table_name: Option<String>,
+        repair: bool,
+    },
+    Honest,
+    ShowAliases,
+    ShowGremlin,
+    StartGremlin(Option<u64>),
+    StopGremlin,
+    ExplainChaos(String),
+    ShowGames,
+    PreferGame(Option<String>),
+    ShowLeaderboard(Option<String>),
+}
+
+pub fn parse(input: &str) -> Option<Result<Command, String>> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let keyword = words.first()?.to_ascii_uppercase();
+
+    let command = match (keyword.as_str(), &words[1..]) {
+        ("SEED", []) => Ok(Command::Seed(None)),
+        ("SEED", [seed]) => seed
+            .parse()
+            .map(|seed| Command::Seed(Some(seed)))
+            .map_err(|_| format!("'{}' is not a seed I can grow chaos from", seed)),
+        ("SEED", _) => Err("Usage: SEED [number]".to_string()),
+        ("CHAOS", []) => Ok(Command::ShowChaos),
+        ("CHAOS", [action, path]) if action.eq_ignore_ascii_case("LOAD") => {
+            Ok(Command::LoadChaos(path.to_string()))
+        }
+        ("CHAOS", [action, path]) if action.eq_ignore_ascii_case("SAVE") => {
+            Ok(Command::SaveChaos(path.to_string()))
+        }
+        ("CHAOS", [action, kind, result, weight]) if action.eq_ignore_ascii_case("SET") => {
+            parse_chaos_weight(kind, result, weight)
+        }
+        ("CHAOS", [action]) if action.eq_ignore_ascii_case("RESET") => Ok(Command::ResetChaos),
+        ("TRASH", []) => Ok(Command::ShowTrash),
+        ("RESTORE", [target]) if target.eq_ignore_ascii_case("ALL") => Ok(Command::Restore(None)),
+        ("RESTORE", [id]) => id
+            .parse()
+            .map(|id| Command::Restore(Some(id)))
+            .map_err(|_| format!("'{}' is not a trash id, look it up with TRASH", id)),
+        ("RESTORE", _) => Err("Usage: RESTORE <id> | RESTORE ALL".to_string()),
+        ("EMPTY", [target]) if target.eq_ignore_ascii_case("TRASH") => Ok(Command::EmptyTrash),
+        ("EXTINGUISH", []) => Ok(Command::Extinguish(None)),
+        ("EXTINGUISH", [table_name]) => Ok(Command::Extinguish(Some(table_name.to_string()))),
+        ("EXTINGUISH", _) => Err("Usage: EXTINGUISH [table]".to_string()),
+        ("LATENCY", []) => Ok(Command::ShowLatency),
+        ("LATENCY", [mode, args @ ..]) => parse_latency(mode, args),
+        ("DECAY", []) => Ok(Command::ShowDecay),
+        ("DECAY", [action, args @ ..]) => parse_decay(action, args),
+        ("JOURNAL", []) => Ok(Command::ShowJournal(None)),
+        ("JOURNAL", [filter]) if filter.eq_ignore_ascii_case("SUMMARY") => {
+            Ok(Command::ShowJournalSummary)
+        }
+        ("JOURNAL", [filter]) => Ok(Command::ShowJournal(Some(filter.to_string()))),
+        ("JOURNAL", _) => Err("Usage: JOURNAL [<table> | <effect> | SUMMARY]".to_string()),
+        ("UNDO", [target]) if target.eq_ignore_ascii_case("CHAOS") => Ok(Command::UndoChaos),
+        ("SCRUB", []) => Ok(Command::Scrub {
+            table_name: None,
+            repair: false,
+        }),
+        ("SCRUB", [action, rest @ ..])
+            if action.eq_ignore_ascii_case("REPAIR") && rest.len() <= 1 =>
+        {
+            Ok(Command::Scrub {
+                table_name: rest.first().map(|table_name| table_name.to_string()),
+                repair: true,
+            })
+        }
+        ("SCRUB", [table_name]) => Ok(Command::Scrub {
+            table_name: Some(table_name.to_string()),
+            repair: false,
+        }),
+        ("SCRUB", _) => Err("Usage: SCRUB [<table>] | SCRUB REPAIR [<table>]".to_string()),
+        ("\\HONEST", []) => Ok(Command::Honest),
+        ("ALIASES", []) => Ok(Command::ShowAliases),
+        ("GREMLIN", []) => Ok(Command::ShowGremlin),
+        ("GREMLIN", [action]) if action.eq_ignore_ascii_case("START") => {
+            Ok(Command::StartGremlin(None))
+        }
+        ("GREMLIN", [action, seconds]) if action.eq_ignore_ascii_case("START") => seconds
+            .parse()
+            .ok()
+            .filter(|seconds| *seconds > 0)
+            .map(|seconds| Command::StartGremlin(Some(seconds)))
+            .ok_or_else(|| format!("'{}' is not a number of seconds", seconds)),
+        ("GREMLIN", [action]) if action.eq_ignore_ascii_case("STOP") => Ok(Command::StopGremlin),
+        ("GREMLIN", _) => Err("Usage: GREMLIN [START [seconds] | STOP]".to_string()),
+        ("UNDO", _) => Err("Usage: UNDO CHAOS".to_string()),
+        ("GAMES", []) => Ok(Command::ShowGames),
+        ("GAME", [name]) if name.eq_ignore_ascii_case("RANDOM") => Ok(Command::PreferGame(None)),
+        ("GAME", [name]) => Ok(Command::PreferGame(Some(name.to_ascii_lowercase()))),
+        ("GAME", _) => Err("Usage: GAME <name> | GAME RANDOM".to_string()),
+        ("LEADERBOARD", []) => Ok(Command::ShowLeaderboard(None)),
+        ("LEADERBOARD", [game]) => Ok(Command::ShowLeaderboard(Some(game.to_ascii_lowercase()))),
+        ("LEADERBOARD", _) => Err("Usage: LEADERBOARD [game]".to_string()),
+        ("EXPLAIN", [target, ..]) if target.eq_ignore_ascii_case("CHAOS") => {
+            // The SQL is taken as typed, not as split into words
+            let sql = input.trim_start()[words[0].len()..].trim_start()[target.len()..].trim();
+            if sql.is_empty() {
+                Err("Usage: EXPLAIN CHAOS <statement>".to_string())
+            } else {
+                Ok(Command::ExplainChaos(sql.to_string()))
+            }
+        }
+        ("DIFFICULTY", []) => Ok(Command::SetDifficulty(None)),
+        ("DIFFICULTY", [level]) => level
+            .parse()
+            .map(|level| Command::SetDifficulty(Some(level))),
+        ("DIFFICULTY", _) => {
+            Err("Usage: DIFFICULTY [boring | easy | normal | nightmare]".to_string())
+        }
+        ("CHAOS", _) => Err(
+            "Usage: CHAOS [LOAD <path> | SAVE <path> | SET <statement> <effect> <weight> | RESET]"
+                .to_string(),
+        ),
+        _ => return None,
+    };
+
+    Some(command)
+}
+
+fn parse_chaos_weight(kind: &str, effect: &str, weight: &str) -> Result<Command, String> {
+    let kind = kind.parse()?;
+    let weight = weight
+        .parse()
+        .map_err(|_| format!("'{}' is not a weight, try a whole number", weight))?;
+
+    Ok(Command::SetChaos(kind, effect.to_ascii_lowercase(), weight))
+}
+
+fn parse_latency(mode: &str, args: &[&str]) -> Result<Command, String> {
+    let usage = "Usage: LATENCY [OFF | FIXED <ms> | UNIFORM <min ms> <max ms> | LONGTAIL <median ms> | PER_ROW <ms>]";
+    let ms = |arg: &str| {
+        arg.parse::<u64>()
+            .map_err(|_| format!("'{}' is not a number of milliseconds", arg))
+    };
+
+    let distribution = match (mode.to_ascii_uppercase().as_str(), args) {
+        ("OFF", []) => LatencyDistribution::Off,
+        ("FIXED", [value]) => LatencyDistribution::Fixed { ms: ms(value)? },
+        ("UNIFORM", [min, max]) => {
+            let (min_ms, max_ms) = (ms(min)?, ms(max)?);
+            if min_ms > max_ms {
+                return Err(
+                    "The minimum can't be slower than the maximum. Not even here.".to_string(),
+                );
+            }
+            LatencyDistribution::Uniform { min_ms, max_ms }
+        }
+        ("LONGTAIL", [median]) => LatencyDistribution::LongTail {
+            median_ms: ms(median)?,
+        },
+        ("PER_ROW", [value]) => {
+            let per_row_ms = value
+                .parse::<f64>()
+                .ok()
+                .filter(|per_row_ms| (0.0..=MAX_PER_ROW_MS).contains(per_row_ms))
+                .ok_or_else(|| {
+                    format!(
+                        "'{}' is not a number of milliseconds between 0 and {}",
+                        value, MAX_PER_ROW_MS
+                    )
+                })?;
+            return Ok(Command::SetRowLatency(per_row_ms));
+        }
+        _ => return Err(usage.to_string()),
+    };
+
+    Ok(Command::SetLatency(distribution))
+}
+
+fn parse_decay(action: &str, args: &[&str]) -> Result<Command, String> {
+    // "table" skips a whole table, "table.column" protects just that column
+    let target = |arg: &str| match arg.split_once('.') {
+        Some((table, column)) => (table.to_string(), Some(column.to_string())),
+        None => (arg.to_string(), None),
+    };
+
+    match (action.to_ascii_uppercase().as_str(), args) {
+        ("OFF", []) => Ok(Command::SetDecayRate(0)),
+        ("RATE", [rate]) => rate
+            .parse()
+            .ok()
+            .filter(|rate| *rate <= 100)
+            .map(Command::SetDecayRate)
+            .ok_or_else(|| format!("'{}' is not a percentage between 0 and 100", rate)),
+        ("SKIP", [arg]) => {
+            let (table, column) = target(arg);
+            Ok(Command::DecaySkip(table, column))
+        }
+        ("UNSKIP", [arg]) => {
+            let (table, column) = target(arg);
+            Ok(Command::DecayUnskip(table, column))
+        }
+        ("LOG", []) => Ok(Command::ShowDecayLog),
+        _ => Err(
+            "Usage: DECAY [OFF | RATE <percent> | SKIP <table>[.<column>] | UNSKIP <table>[.<column>] | LOG]"
+                .to_string(),
+        ),
+    }
+}
+
+pub fn run(db: &mut WackyDB, command: Command) {
+    match command {
+        Command::Seed(None) => {
+            say!("Current chaos seed: {}", db.seed);
+        }
+        Command::Seed(Some(seed)) => {
+            db.reseed(seed);
+            say!(
+                "Chaos reseeded with {}. History is about to repeat itself.",
+                seed
+            );
+        }
+        Command::ShowChaos => {
+            say!("{}", db.chaos_profile);
+            let names: Vec<&str> = db.effects.names().collect();
+            say!("Registered effects: {}", names.join(", "));
+        }
+        Command::LoadChaos(path) => match ChaosProfile::load(&path).and_then(|profile| {
+            profile.check(&db.effects)?;
+            Ok(profile)
+        }) {
+            Ok(profile) => {
+                db.chaos_profile = profile;
+                say!("Chaos profile loaded from '{}'.", path);
+            }
+            Err(e) => say!("Couldn't load chaos profile '{}': {}", path, e),
+        },
+        Command::SaveChaos(path) => match db.chaos_profile.save(&path) {
+            Ok(()) => say!("Chaos profile saved to '{}'.", path),
+            Err(e) => say!("Couldn't save chaos profile '{}': {}", path, e),
+        },
+        Command::SetChaos(kind, effect, weight) => {
+            if effect != PROCEED_AS_NORMAL && !db.effects.contains(&effect) {
+                say!("'{}' is not a kind of chaos I know", effect);
+                return;
+            }
+            db.chaos_profile.set_weight(kind, &effect, weight);
+            say!("{} now rolls {} with weight {}.", kind, effect, weight);
+        }
+        Command::ResetChaos => {
+            db.chaos_profile = ChaosProfile::default();
+            say!("Chaos profile is back to its usual wacky self.");
+        }
+        Command::ShowTrash => show_trash(db),
+        Command::Restore(Some(id)) => match trash::restore(&db.conn, id, db.difficulty) {
+            Ok(table_name) => say!("Item {} is back in '{}'. Phew!", id, table_name),
+            Err(e) => say!("{}", e),
+        },
+        Command::Restore(None) => restore_all(db),
+        Command::EmptyTrash => match trash::empty(&db.conn) {
+            Ok(count) => say!("Took out the trash. {} item(s) are gone for good.", count),
+            Err(e) => say!("Couldn't empty the trash: {}", e),
+        },
+        Command::Extinguish(table_name) => {
+            match fire::extinguish(&db.conn, table_name.as_deref()) {
+                Ok(0) => say!("Nothing to put out. Everything is (for now) fine."),
+                Ok(count) => say!("🧯 Put out {} fire(s). Stay safe out there.", count),
+                Err(e) => say!("The extinguisher jammed: {}", e),
+            }
+        }
+        Command::ShowLatency => {
+            say!("Latency: {}", db.latency);
+        }
+        Command::SetLatency(LatencyDistribution::Off) => {
+            db.latency = LatencyConfig::default();
+            say!("Latency: {}", db.latency);
+        }
+        Command::SetLatency(distribution) => {
+            db.latency.distribution = distribution;
+            say!("Latency: {}", db.latency);
+        }
+        Command::SetRowLatency(per_row_ms) => {
+            db.latency.per_row_ms = per_row_ms;
+            say!("Latency: {}", db.latency);
+        }
+        Command::ShowDecay => {
+            say!("Decay: {}", db.decay);
+        }
+        Command::SetDecayRate(rate) => {
+            db.decay.rate = rate;
+            restart_decay(db);
+        }
+        Command::DecaySkip(table, None) => {
+            db.decay.skip_tables.insert(table);
+            restart_decay(db);
+        }
+        Command::DecaySkip(table, Some(column)) => {
+            db.decay.skip_columns.insert((table, column));
+            restart_decay(db);
+        }
+        Command::DecayUnskip(table, None) => {
+            db.decay.skip_tables.remove(&table);
+            restart_decay(db);
+        }
+        Command::DecayUnskip(table, Some(column)) => {
+            db.decay.skip_columns.remove(&(table, column));
+            restart_decay(db);
+        }
+        Command::ShowDecayLog => match decay::log(&db.conn) {
+            Ok(entries) if entries.is_empty() => say!("Nothing has decayed. Yet."),
+            Ok(entries) => {
+                for entry in entries {
+                    say!("{}", entry);
+                }
+            }
+            Err(e) => say!("Couldn't read the decay log: {}", e),
+        },
+        Command::ShowJournal(filter) => match journal::entries(&db.conn, filter.as_deref()) {
+            Ok(entries) if entries.is_empty() => say!("The journal is empty. How boring."),
+            Ok(entries) => {
+                for entry in entries {
+                    say!("{}", entry);
+                }
+            }
+            Err(e) => say!("Couldn't read the journal: {}", e),
+        },
+        Command::SetDifficulty(None) => {
+            say!("Difficulty: {}", db.difficulty);
+        }
+        Command::SetDifficulty(Some(difficulty)) => {
+            db.difficulty = difficulty;
+            if difficulty.is_boring() && db.gremlin.take().is_some() {
+                say!("👹 The gremlin got bored and left.");
+            }
+            // Decay rots at the new difficulty's pace, or not at all
+            if let Err(e) = db.restart_decay() {
+                say!("{}", e);
+            }
+            match difficulty {
+                Difficulty::Boring => say!("Fine. No chaos. Just a plain old database. 😐"),
+                Difficulty::Easy => say!("Chaos is taking it easy on you."),
+                Difficulty::Normal => say!("Back to the usual wackiness."),
+                Difficulty::Nightmare => say!("You asked for this. 😈"),
+            }
+        }
+        Command::UndoChaos => match db.undo_chaos() {
+            Ok(Some(snapshot)) => say!(
+                "Undid {} on '{}' from `{}`. Anything you did since is still there.",
+                snapshot.effect,
+                snapshot.table_name,
+                snapshot.statement
+            ),
+            Ok(None) => say!("There's no chaos left to undo. Enjoy it while it lasts."),
+            Err(e) => say!("Couldn't undo the chaos: {}", e),
+        },
+        Command::Scrub { table_name, repair } => scrub(db, table_name.as_deref(), repair),
+        Command::Honest => honest(db),
+        Command::ExplainChaos(sql) => explain_chaos(db, &sql),
+        Command::ShowGames => {
+            let names: Vec<&str> = db.games.names().collect();
+            say!("Games: {}", names.join(", "));
+            match db.games.preferred() {
+                Some(name) => say!("Gaming time always means {}.", name),
+                None => say!("Gaming time picks one at random."),
+            }
+        }
+        Command::PreferGame(name) => match db.games.prefer(name.as_deref()) {
+            Ok(()) => match name {
+                Some(name) => say!("🎮 {} it is, every time.", name),
+                None => say!("🎮 Surprise me, then."),
+            },
+            Err(e) => say!("{}", e),
+        },
+        Command::ShowLeaderboard(game) => show_leaderboard(db, game.as_deref()),
+        Command::ShowGremlin => match &db.gremlin {
+            Some(gremlin) => say!(
+                "👹 A gremlin is loose, up to something every {}s. GREMLIN STOP to catch it.",
+                gremlin.interval.as_secs()
+            ),
+            None => say!("No gremlins around. GREMLIN START if you're feeling brave."),
+        },
+        Command::StartGremlin(seconds) => start_gremlin(db, seconds),
+        Command::StopGremlin => match db.gremlin.take() {
+            Some(_) => say!("🪤 Caught the gremlin. It won't bother you again. Probably."),
+            None => say!("There's no gremlin to stop. That you know of."),
+        },
+        Command::ShowAliases => match drift::list(&db.conn) {
+            Ok(aliases) if aliases.is_empty() => say!("The schema hasn't drifted. Yet."),
+            Ok(aliases) => {
+                for alias in aliases {
+                    let (old, new) = match &alias.table_name {
+                        Some(table_name) => (
+                            format!("{}.{}", table_name, alias.old_name),
+                            format!("{}.{}", table_name, alias.new_name),
+                        ),
+                        None => (alias.old_name, alias.new_name),
+                    };
+                    say!("{}  {} -> {}", alias.renamed_at, old, new);
+                }
+            }
+            Err(e) => say!("Couldn't read the alias map: {}", e),
+        },
+        Command::ShowJournalSummary => match journal::summary(&db.conn) {
+            Ok(summary) if summary.is_empty() => say!("The journal is empty. How boring."),
+            Ok(summary) => {
+                say!("{:<18} {:>6} {:>6}", "effect", "times", "rows");
+                for row in summary {
+                    say!(
+                        "{:<18} {:>6} {:>6}",
+                        row.effect,
+                        row.times,
+                        row.rows_affected
+                    );
+                }
+            }
+            Err(e) => say!("Couldn't read the journal: {}", e),
+        },
+    }
+}
+
+fn show_leaderboard(db: &WackyDB, game: Option<&str>) {
+    let placings = match leaderboard::top(&db.conn, game, leaderboard::PLACES_SHOWN) {
+        Ok(placings) => placings,
+        Err(e) => {
+            say!("Couldn't read the leaderboard: {}", e);
+            return;
+        }
+    };
+    if placings.is_empty() {
+        match game {
+            Some(game) => say!("Nobody has played {} yet. Keep running statements.", game),
+            None => say!("Nobody has played a game yet. Keep running statements."),
+        }
+        return;
+    }
+
+    let mut current_game = None;
+    for placing in placings {
+        if current_game.as_ref() != Some(&placing.score.game) {
+            say!("🏆 {}", placing.score.game);
+            current_game = Some(placing.score.game.clone());
+        }
+        say!("{}", placing);
+    }
+}
+
+fn show_trash(db: &WackyDB) {
+    let items = match trash::list(&db.conn) {
+        Ok(items) => items,
+        Err(e) => {
+            say!("Couldn't rummage through the trash: {}", e);
+            return;
+        }
+    };
+
+    if items.is_empty() {
+        say!("The trash is empty. Suspiciously clean.");
+        return;
+    }
+
+    for item in items {
+        say!(
+            "{:>4}  {:<20} {}  {}",
+            item.id,
+            item.source_table,
+            item.trashed_at,
+            serde_json::Value::Object(item.data)
+        );
+    }
+}
+
+fn restore_all(db: &WackyDB) {
+    let items = match trash::list(&db.conn) {
+        Ok(items) => items,
+        Err(e) => {
+            say!("Couldn't rummage through the trash: {}", e);
+            return;
+        }
+    };
+
+    let mut restored = 0;
+    for item in items {
+        match trash::restore(&db.conn, item.id, db.difficulty) {
+            Ok(_) => restored += 1,
+            Err(e) => say!("{}", e),
+        }
+    }
+    say!("Restored {} item(s) from the trash.", restored);
+}
+
+fn scrub(db: &WackyDB, table_name: Option<&str>, repair: bool) {
+    if let Some(table_name) = table_name {
+        if !matches!(db.table_exists(table_name), Ok(true)) {
+            say!("Table '{}' does not exist.", table_name);
+            return;
+        }
+    }
+
+    let corrupt = match bitrot::scrub(&db.conn, table_name) {
+        Ok(corrupt) => corrupt,
+        Err(e) => {
+            say!("Couldn't scrub: {}", e);
+            return;
+        }
+    };
+
+    if corrupt.is_empty() {
+        say!("✨ Every checksum adds up. Squeaky clean.");
+        return;
+    }
+
+    for row in &corrupt {
+        for (column, expected, found) in row.differences() {
+            say!(
+                "🔍 '{}' row {}: {} should be {}, found {}",
+                row.table_name,
+                row.rowid,
+                column,
+                expected,
+                found
+            );
+        }
+    }
+
+    if !repair {
+        say!(
+            "Found {} rotten row(s). SCRUB REPAIR puts them back the way they were.",
+            corrupt.len()
+        );
+        return;
+    }
+
+    let mut repaired = 0;
+    for row in &corrupt {
+        match bitrot::repair(&db.conn, row) {
+            Ok(()) => {
+                repaired += 1;
+                db.journal("SCRUB REPAIR", "scrub", &row.table_name, 1, "repaired");
+            }
+            Err(e) => say!(
+                "Couldn't repair '{}' row {}: {}",
+                row.table_name,
+                row.rowid,
+                e
+            ),
+        }
+    }
+    say!("🩹 Repaired {} rotten row(s).", repaired);
+}
+
+// Shows the last SELECT as it was shown, next to what was really there
+fn honest(db: &WackyDB) {
+    let Some(answer) = &db.last_answer else {
+        say!("You haven't asked me anything yet, so I haven't lied about anything yet.");
+        return;
+    };
+
+    let Some(lie) = &answer.shown.lie else {
+        say!("`{}` got the honest truth. This time.", answer.statement);
+        return;
+    };
+
+    say!("`{}`", answer.statement);
+    say!("What I told you ({}):", lie);
+    say!("{}", answer.shown);
+    say!("What's really there:");
+    say!("{}", answer.truth);
+}
+
+// Sizes up a statement the way the chaos stage would, without running it
+fn explain_chaos(db: &WackyDB, sql: &str) {
+    let mut explained: Vec<(String, String)> = Vec::new();
+    for mut operation in crate::plan_sql(&GenericDialect {}, sql) {
+        // A multi-row INSERT runs one row at a time, but it's still the one statement to explain
+        let statement = (operation.sql.clone(), operation.table_name.clone());
+        if explained.contains(&statement) {
+            continue;
+        }
+        explained.push(statement);
+
+        // Old names count for what they point to now, same as when the statement really runs
+        if let Ok(Some(current)) = drift::resolve_table(&db.conn, &operation.table_name) {
+            operation.table_name = current;
+        }
+        if operation.kind != StatementKind::Create
+            && !db.table_exists(&operation.table_name).unwrap_or(false)
+        {
+            say!(
+                "Table '{}' does not exist, so nothing's at stake.",
+                operation.table_name
+            );
+            continue;
+        }
+        let _ = drift::resolve_columns(&db.conn, &mut operation);
+
+        let risk = match risk::assess(&db.conn, &operation) {
+            Ok(risk) => risk,
+            Err(e) => {
+                say!("Couldn't size up `{}`: {}", operation.sql, e);
+                continue;
+            }
+        };
+        say!(
+            "{} on '{}', {} row(s) at stake:",
+            operation.kind,
+            operation.table_name,
+            risk.rows_at_stake
+        );
+        say!("{}", risk);
+        if db.difficulty.is_boring() {
+            say!("Not that it matters on boring. Nothing wacky happens, whatever the risk.");
+        } else {
+            let chance = db
+                .chaos_profile
+                .chaos_chance(operation.kind, db.chaos_frequency(&risk));
+            say!(
+                "Odds of something wacky happening: {:.0}% on {}.",
+                chance * 100.0,
+                db.difficulty
+            );
+        }
+    }
+}
+
+// Puts the changed policy to work, and shows it
+fn restart_decay(db: &mut WackyDB) {
+    if let Err(e) = db.restart_decay() {
+        say!("{}", e);
+    }
+    say!("Decay: {}", db.decay);
+}
+
+fn start_gremlin(db: &mut WackyDB, seconds: Option<u64>) {
+    if db.difficulty.is_boring() {
+        say!("Gremlins don't do boring. Pick another DIFFICULTY first.");
+        return;
+    }
+    if db.gremlin.is_some() {
+        say!("👹 One gremlin is plenty.");
+        return;
+    }
+    if !background::can_reach(&db.path) {
+        say!("👹 The gremlin can't find its way into a database that only lives in memory.");
+        return;
+    }
+    if background::sits_out_sessions() {
+        say!("👹 Gremlins are camera shy, they stay out of recorded and replayed sessions.");
+        return;
+    }
+
+    let interval = seconds.map_or(gremlin::DEFAULT_INTERVAL, Duration::from_secs);
+    match gremlin::start(&db.path, db.rng.gen(), interval) {
+        Ok(gremlin) => {
+            db.gremlin = Some(gremlin);
+            say!(
+                "👹 A gremlin crawled into the database. It'll be up to something every {}s.",
+                interval.as_secs()
+            );
+        }
+        Err(e) => say!("The gremlin wouldn't come out to play: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sql_is_left_for_the_parser() {
+        assert!(parse("SELECT * FROM pets").is_none());
+        assert!(parse("EXPLAIN SELECT * FROM pets").is_none());
+        assert!(parse("   ").is_none());
+    }
+
+    #[test]
+    fn keywords_are_case_insensitive() {
+        assert!(matches!(
+            parse("chaos set insert bit_rot 5"),
+            Some(Ok(Command::SetChaos(StatementKind::Insert, ref effect, 5))) if effect == "bit_rot"
+        ));
+        assert!(matches!(
+            parse("Difficulty NIGHTMARE"),
+            Some(Ok(Command::SetDifficulty(Some(Difficulty::Nightmare))))
+        ));
+        assert!(matches!(parse("undo chaos"), Some(Ok(Command::UndoChaos))));
+    }
+
+    #[test]
+    fn bad_arguments_come_back_with_a_reason() {
+        assert!(matches!(parse("SEED banana"), Some(Err(_))));
+        assert!(matches!(parse("RESTORE"), Some(Err(_))));
+        assert!(matches!(parse("DECAY RATE 101"), Some(Err(_))));
+        assert!(matches!(parse("GREMLIN START 0"), Some(Err(_))));
+        assert!(matches!(parse("LATENCY UNIFORM 10 5"), Some(Err(_))));
+        assert!(matches!(parse("LATENCY PER_ROW inf"), Some(Err(_))));
+        assert!(matches!(parse("LATENCY PER_ROW 1e300"), Some(Err(_))));
+        assert!(matches!(parse("LATENCY PER_ROW NaN"), Some(Err(_))));
+        assert!(matches!(
+            parse("LATENCY PER_ROW 2.5"),
+            Some(Ok(Command::SetRowLatency(per_row_ms))) if per_row_ms == 2.5
+        ));
+    }
+
+    #[test]
+    fn explain_chaos_keeps_the_statement_as_typed() {
+        assert!(matches!(
+            parse("explain  chaos  INSERT INTO pets VALUES (1, 'a  b')\n"),
+            Some(Ok(Command::ExplainChaos(ref sql))) if sql == "INSERT INTO pets VALUES (1, 'a  b')"
+        ));
+        assert!(matches!(parse("EXPLAIN CHAOS"), Some(Err(_))));
+    }
+
+    #[test]
+    fn games_are_picked_by_name_or_at_random() {
+        assert!(matches!(
+            parse("GAME Catching"),
+            Some(Ok(Command::PreferGame(Some(ref name)))) if name == "catching"
+        ));
+        assert!(matches!(
+            parse("game random"),
+            Some(Ok(Command::PreferGame(None)))
+        ));
+        assert!(matches!(parse("GAME"), Some(Err(_))));
+    }
+
+    #[test]
+    fn the_leaderboard_shows_every_game_or_just_one() {
+        assert!(matches!(
+            parse("leaderboard"),
+            Some(Ok(Command::ShowLeaderboard(None)))
+        ));
+        assert!(matches!(
+            parse("LEADERBOARD Catching"),
+            Some(Ok(Command::ShowLeaderboard(Some(ref game)))) if game == "catching"
+        ));
+        assert!(matches!(parse("LEADERBOARD a b"), Some(Err(_))));
+    }
+
+    #[test]
+    fn scrub_can_repair_one_table_or_all_of_them() {
+        assert!(matches!(
+            parse("SCRUB REPAIR pets"),
+            Some(Ok(Command::Scrub { table_name: Some(ref table_name), repair: true })) if table_name == "pets"
+        ));
+        assert!(matches!(
+            parse("SCRUB"),
+            Some(Ok(Command::Scrub {
+                table_name: None,
+                repair: false
+            }))
+        ));
+    }
+}
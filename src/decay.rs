@@ -0,0 +1,349 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Duration;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rusqlite::{params, Connection};
+
+use crate::background::{self, Background};
+use crate::difficulty::Difficulty;
+use crate::rows::{self, RowData};
+use crate::session::say;
+use crate::{bitrot, fire};
+
+pub const DECAY_LOG_TABLE: &str = "_wacky_decay_log";
+// How often data gets a chance to rot, whether anyone's typing or not
+pub const TICK: Duration = Duration::from_secs(5);
+
+// Which data is allowed to rot away, and how fast
+#[derive(Default, Clone)]
+pub struct DecayPolicy {
+    // Chance, in percent, that each table loses a bit of data on every tick
+    pub rate: u32,
+    pub skip_tables: BTreeSet<String>,
+    // (table, column) pairs that never lose their values. Rows of a table with protected
+    // columns aren't dropped outright, their other values fade to NULL instead.
+    pub skip_columns: BTreeSet<(String, String)>,
+}
+
+pub struct DecayLogEntry {
+    pub id: i64,
+    pub table_name: String,
+    // None when the whole row was dropped
+    pub column_name: Option<String>,
+    pub data: RowData,
+    pub dropped_at: String,
+}
+
+impl DecayPolicy {
+    pub fn is_off(&self) -> bool {
+        self.rate == 0
+    }
+
+    fn protected_columns(&self, table_name: &str) -> Vec<&str> {
+        self.skip_columns
+            .iter()
+            .filter(|(table, _)| table == table_name)
+            .map(|(_, column)| column.as_str())
+            .collect()
+    }
+}
+
+impl fmt::Display for DecayPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_off() {
+            write!(f, "off")?;
+        } else {
+            write!(
+                f,
+                "{}% chance per table every {}s",
+                self.rate,
+                TICK.as_secs()
+            )?;
+        }
+
+        if !self.skip_tables.is_empty() {
+            let tables: Vec<&str> = self.skip_tables.iter().map(|t| t.as_str()).collect();
+            write!(f, "\nSkipped tables: {}", tables.join(", "))?;
+        }
+        if !self.skip_columns.is_empty() {
+            let columns: Vec<String> = self
+                .skip_columns
+                .iter()
+                .map(|(table, column)| format!("{}.{}", table, column))
+                .collect();
+            write!(f, "\nProtected columns: {}", columns.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for DecayLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = match &self.column_name {
+            Some(column) => format!("{}.{}", self.table_name, column),
+            None => self.table_name.clone(),
+        };
+        write!(
+            f,
+            "{:>4}  {}  {:<20} {}",
+            self.id,
+            self.dropped_at,
+            location,
+            serde_json::Value::Object(self.data.clone())
+        )
+    }
+}
+
+pub fn ensure_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY,
+                table_name TEXT NOT NULL,
+                column_name TEXT,
+                row_data TEXT NOT NULL,
+                dropped_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            DECAY_LOG_TABLE
+        ),
+        [],
+    )?;
+
+    Ok(())
+}
+
+// Starts the data rotting in the background, on a connection of its own, a tick at a time.
+// It works from a copy of the policy, so it has to be started over when the policy changes.
+pub fn start(
+    path: &str,
+    seed: u64,
+    policy: DecayPolicy,
+    difficulty: Difficulty,
+) -> Result<Background, Box<dyn std::error::Error>> {
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(background::BUSY_TIMEOUT)?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    Ok(Background::start(TICK, move || {
+        match decay(&conn, &mut rng, &policy, difficulty) {
+            Ok(lost) => {
+                for entry in lost {
+                    let (outcome, message) = match &entry.column_name {
+                        Some(column) => (
+                            format!("value in '{}' decayed", column),
+                            format!(
+                                "🍂 A value in '{}.{}' quietly decayed away. (DECAY LOG)",
+                                entry.table_name, column
+                            ),
+                        ),
+                        None => (
+                            "row decayed".to_string(),
+                            format!(
+                                "🍂 A row of '{}' quietly decayed away. (DECAY LOG)",
+                                entry.table_name
+                            ),
+                        ),
+                    };
+                    background::report(&conn, "decay", &entry.table_name, &outcome, &message);
+                }
+            }
+            Err(e) => say!("\nEven the decay is decaying: {}", e),
+        }
+    }))
+}
+
+// Lets the data rot for one tick. Returns what was lost, which is also written to the log.
+pub fn decay(
+    conn: &Connection,
+    rng: &mut StdRng,
+    policy: &DecayPolicy,
+    difficulty: Difficulty,
+) -> Result<Vec<DecayLogEntry>, Box<dyn std::error::Error>> {
+    if policy.is_off() {
+        return Ok(Vec::new());
+    }
+
+    let rate = difficulty.scale(policy.rate);
+    let mut lost = Vec::new();
+    for table_name in rows::user_tables(conn)? {
+        // Burning tables are read-only, even to the decay
+        if policy.skip_tables.contains(&table_name)
+            || fire::guard_write(conn, &table_name).is_err()
+            || rng.gen_range(0..100) >= rate
+        {
+            continue;
+        }
+
+        let rows = rows::fetch_rows(conn, &table_name, "1 = 1")?;
+        let Some((rowid, data)) = rows.choose(rng) else {
+            continue;
+        };
+
+        let protected = policy.protected_columns(&table_name);
+        let column_name = if protected.is_empty() {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE rowid = ?1", table_name),
+                [rowid],
+            )?;
+            bitrot::forget_rows(conn, &table_name, &[*rowid])?;
+            None
+        } else {
+            // Only values that are still there and allowed to be NULL can fade away
+            let fading: Vec<String> = rows::column_info(conn, &table_name)?
+                .into_iter()
+                .filter(|column| !column.not_null && !column.primary_key)
+                .filter(|column| !protected.contains(&column.name.as_str()))
+                .filter(|column| data.get(&column.name).is_some_and(|v| !v.is_null()))
+                .map(|column| column.name)
+                .collect();
+            let Some(column) = fading.choose(rng) else {
+                continue;
+            };
+
+            conn.execute(
+                &format!(
+                    "UPDATE {} SET {} = NULL WHERE rowid = ?1",
+                    table_name, column
+                ),
+                [rowid],
+            )?;
+            // Decay keeps its own log, SCRUB is only after bit rot
+            bitrot::checksum_row(conn, &table_name, *rowid)?;
+            Some(column.clone())
+        };
+
+        lost.push(log_drop(conn, &table_name, column_name, data)?);
+    }
+
+    Ok(lost)
+}
+
+fn log_drop(
+    conn: &Connection,
+    table_name: &str,
+    column_name: Option<String>,
+    data: &RowData,
+) -> Result<DecayLogEntry, Box<dyn std::error::Error>> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (table_name, column_name, row_data) VALUES (?1, ?2, ?3)",
+            DECAY_LOG_TABLE
+        ),
+        params![table_name, column_name, serde_json::to_string(data)?],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    let dropped_at = conn.query_row(
+        &format!("SELECT dropped_at FROM {} WHERE id = ?1", DECAY_LOG_TABLE),
+        [id],
+        |row| row.get(0),
+    )?;
+
+    Ok(DecayLogEntry {
+        id,
+        table_name: table_name.to_string(),
+        column_name,
+        data: data.clone(),
+        dropped_at,
+    })
+}
+
+pub fn log(conn: &Connection) -> Result<Vec<DecayLogEntry>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, table_name, column_name, row_data, dropped_at FROM {} ORDER BY id",
+        DECAY_LOG_TABLE
+    ))?;
+    let mut rows = stmt.query([])?;
+
+    let mut entries = Vec::new();
+    while let Some(row) = rows.next()? {
+        let row_data: String = row.get(3)?;
+        entries.push(DecayLogEntry {
+            id: row.get(0)?,
+            table_name: row.get(1)?,
+            column_name: row.get(2)?,
+            data: serde_json::from_str(&row_data)?,
+            dropped_at: row.get(4)?,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rows::fixtures;
+
+    // The usual pets, and vets to spare
+    fn pets_and_vets() -> Connection {
+        let conn = fixtures::pets();
+        conn.execute_batch(
+            "CREATE TABLE vets (id INTEGER PRIMARY KEY, name TEXT);
+             INSERT INTO vets VALUES (1, 'ana'), (2, 'bo'), (3, 'cy');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn lost(seed: u64, policy: &DecayPolicy, ticks: usize) -> Vec<(RowData, Option<String>)> {
+        let conn = pets_and_vets();
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..ticks)
+            .flat_map(|_| decay(&conn, &mut rng, policy, Difficulty::Normal).unwrap())
+            .map(|entry| (entry.data, entry.column_name))
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_loses_the_same_data() {
+        let policy = DecayPolicy {
+            rate: 50,
+            ..DecayPolicy::default()
+        };
+
+        assert!(!lost(3, &policy, 10).is_empty());
+        assert_eq!(lost(3, &policy, 10), lost(3, &policy, 10));
+    }
+
+    #[test]
+    fn skipped_tables_and_columns_are_spared() {
+        let mut policy = DecayPolicy {
+            rate: 100,
+            ..DecayPolicy::default()
+        };
+        policy.skip_tables.insert("vets".to_string());
+        policy
+            .skip_columns
+            .insert(("pets".to_string(), "id".to_string()));
+
+        let conn = pets_and_vets();
+        let mut rng = StdRng::seed_from_u64(3);
+        let entries = decay(&conn, &mut rng, &policy, Difficulty::Normal).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].table_name, "pets");
+        assert_eq!(entries[0].column_name.as_deref(), Some("name"));
+        assert_eq!(log(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn burning_tables_are_spared() {
+        let policy = DecayPolicy {
+            rate: 100,
+            ..DecayPolicy::default()
+        };
+        let conn = pets_and_vets();
+        fire::ignite(&conn, "vets").unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+
+        for _ in 0..5 {
+            for entry in decay(&conn, &mut rng, &policy, Difficulty::Normal).unwrap() {
+                assert_eq!(entry.table_name, "pets");
+            }
+        }
+        assert_eq!(rows::fetch_rows(&conn, "vets", "1 = 1").unwrap().len(), 3);
+    }
+}
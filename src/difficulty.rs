@@ -0,0 +1,100 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Difficulty {
+    // No chaos at all, just a plain old SQLite shell
+    Boring,
+    Easy,
+    #[default]
+    Normal,
+    Nightmare,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Boring,
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Nightmare,
+    ];
+
+    pub fn is_boring(&self) -> bool {
+        *self == Difficulty::Boring
+    }
+
+    // How often chaos strikes, in percent of the chaos profile's weights
+    pub fn frequency(&self) -> u32 {
+        match self {
+            Difficulty::Boring => 0,
+            Difficulty::Easy => 50,
+            Difficulty::Normal => 100,
+            Difficulty::Nightmare => 300,
+        }
+    }
+
+    // How bad it gets when it does, in percent of the usual damage
+    pub fn severity(&self) -> u32 {
+        match self {
+            Difficulty::Boring => 0,
+            Difficulty::Easy => 50,
+            Difficulty::Normal => 100,
+            Difficulty::Nightmare => 200,
+        }
+    }
+
+    // Scales a chance or an amount by the severity
+    pub fn scale(&self, amount: u32) -> u32 {
+        amount * self.severity() / 100
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Boring => "boring",
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Nightmare => "nightmare",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "'{}' is not a difficulty. Pick boring, easy, normal or nightmare",
+                    s
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difficulties_parse_back_from_their_names() {
+        for difficulty in Difficulty::ALL {
+            assert_eq!(difficulty.to_string().parse(), Ok(difficulty));
+        }
+        assert_eq!("NIGHTMARE".parse(), Ok(Difficulty::Nightmare));
+        assert!("impossible".parse::<Difficulty>().is_err());
+    }
+
+    #[test]
+    fn harder_difficulties_hit_harder() {
+        assert_eq!(Difficulty::Boring.scale(100), 0);
+        assert_eq!(Difficulty::Easy.scale(100), 50);
+        assert_eq!(Difficulty::Normal.scale(100), 100);
+        assert_eq!(Difficulty::Nightmare.scale(100), 200);
+    }
+}
@@ -0,0 +1,237 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use rusqlite::{params, Connection, OptionalExtension};
+use sqlparser::ast::{AssignmentTarget, Ident};
+
+use crate::chaos::PendingOperation;
+use crate::{bitrot, fire, rows, trash};
+
+// Every rename schema drift ever did, so old names can still be traced to new ones
+pub const ALIAS_TABLE: &str = "_wacky_aliases";
+
+// What every developer has named a table at some point
+const SUFFIXES: [&str; 8] = [
+    "v2",
+    "new",
+    "old",
+    "final",
+    "final_FINAL",
+    "legacy",
+    "tmp",
+    "backup",
+];
+
+pub struct Alias {
+    // Only set for columns: the table the column belongs to, by its current name
+    pub table_name: Option<String>,
+    pub old_name: String,
+    pub new_name: String,
+    pub renamed_at: String,
+}
+
+pub fn ensure_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY,
+                table_name TEXT,
+                old_name TEXT NOT NULL,
+                new_name TEXT NOT NULL,
+                renamed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            ALIAS_TABLE
+        ),
+        [],
+    )?;
+
+    Ok(())
+}
+
+// Renames the table, or one of its columns, in the real schema. WackyDB's own tables are
+// kept up to date, the user's queries are not. Returns the table's new name if it was the
+// table that got renamed.
+pub fn drift(
+    conn: &Connection,
+    rng: &mut StdRng,
+    table_name: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let suffix = SUFFIXES.choose(rng).expect("there are suffixes");
+
+    if rng.gen_bool(0.5) {
+        let taken = rows::user_tables(conn)?;
+        let to = unused_name(&format!("{}_{}", table_name, suffix), &taken);
+        conn.execute(&format!("ALTER TABLE {} RENAME TO {}", table_name, to), [])?;
+
+        conn.execute(
+            &format!(
+                "INSERT INTO {} (table_name, old_name, new_name) VALUES (NULL, ?1, ?2)",
+                ALIAS_TABLE
+            ),
+            [table_name, &to],
+        )?;
+        // The table's column aliases move along with it
+        conn.execute(
+            &format!(
+                "UPDATE {} SET table_name = ?2 WHERE table_name = ?1",
+                ALIAS_TABLE
+            ),
+            [table_name, &to],
+        )?;
+        fire::rename_table(conn, table_name, &to)?;
+        trash::rename_table(conn, table_name, &to)?;
+        bitrot::rename_table(conn, table_name, &to)?;
+
+        return Ok(Some(to));
+    }
+
+    let columns = rows::column_names(conn, table_name)?;
+    let from = columns
+        .choose(rng)
+        .ok_or("a table without columns")?
+        .clone();
+    let to = unused_name(&format!("{}_{}", from, suffix), &columns);
+    conn.execute(
+        &format!(
+            "ALTER TABLE {} RENAME COLUMN {} TO {}",
+            table_name, from, to
+        ),
+        [],
+    )?;
+
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (table_name, old_name, new_name) VALUES (?1, ?2, ?3)",
+            ALIAS_TABLE
+        ),
+        [table_name, &from, &to],
+    )?;
+    trash::rename_column(conn, table_name, &from, &to)?;
+    bitrot::rename_column(conn, table_name, &from, &to)?;
+
+    Ok(None)
+}
+
+fn unused_name(name: &str, taken: &[String]) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while taken
+        .iter()
+        .any(|taken| taken.eq_ignore_ascii_case(&candidate))
+    {
+        candidate = format!("{}{}", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+// Follows a table's renames to the name it has now, if it had any
+pub fn resolve_table(
+    conn: &Connection,
+    table_name: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    follow(conn, None, table_name)
+}
+
+// Follows a column's renames within a table to the name it has now, if it had any
+pub fn resolve_column(
+    conn: &Connection,
+    table_name: &str,
+    column: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    follow(conn, Some(table_name), column)
+}
+
+fn follow(
+    conn: &Connection,
+    table_name: Option<&str>,
+    name: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut current = name.to_string();
+    let mut renamed = false;
+    // Every rename gets a fresh name, so the chain always ends. The limit is for peace of mind.
+    for _ in 0..100 {
+        let next: Option<String> = conn
+            .query_row(
+                &format!(
+                    "SELECT new_name FROM {} WHERE table_name IS ?1 AND old_name = ?2
+                     ORDER BY id DESC LIMIT 1",
+                    ALIAS_TABLE
+                ),
+                params![table_name, current],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match next {
+            Some(next) => {
+                current = next;
+                renamed = true;
+            }
+            None => break,
+        }
+    }
+
+    Ok(renamed.then_some(current))
+}
+
+// Points the operation's columns at their current names. Returns the renames it made.
+pub fn resolve_columns(
+    conn: &Connection,
+    operation: &mut PendingOperation,
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let table_name = operation.table_name.clone();
+    let existing = rows::column_names(conn, &table_name)?;
+    let mut renamed = Vec::new();
+    let mut resolve = |column: &str| -> Result<Option<String>, Box<dyn std::error::Error>> {
+        if existing.iter().any(|existing| existing == column) {
+            return Ok(None);
+        }
+        let current = resolve_column(conn, &table_name, column)?;
+        if let Some(current) = &current {
+            let rename = (column.to_string(), current.clone());
+            if !renamed.contains(&rename) {
+                renamed.push(rename);
+            }
+        }
+        Ok(current)
+    };
+
+    let mut projection = Vec::new();
+    for item in operation.projection.split(", ") {
+        projection.push(resolve(item)?.unwrap_or_else(|| item.to_string()));
+    }
+    operation.projection = projection.join(", ");
+
+    for assignment in operation
+        .assignments
+        .iter_mut()
+        .chain(operation.where_clauses.iter_mut())
+    {
+        if let AssignmentTarget::ColumnName(name) = &mut assignment.target {
+            if let [ident] = name.0.as_mut_slice() {
+                if let Some(current) = resolve(&ident.value)? {
+                    *ident = Ident::new(current);
+                }
+            }
+        }
+    }
+
+    Ok(renamed)
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<Alias>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT table_name, old_name, new_name, renamed_at FROM {} ORDER BY id",
+        ALIAS_TABLE
+    ))?;
+    let aliases = stmt
+        .query_map([], |row| {
+            Ok(Alias {
+                table_name: row.get(0)?,
+                old_name: row.get(1)?,
+                new_name: row.get(2)?,
+                renamed_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<Alias>, _>>()?;
+
+    Ok(aliases)
+}
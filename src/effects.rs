@@ -0,0 +1,498 @@
+use crate::chaos::{
+    ChaosContext, ChaosEffect, EffectOutcome, EffectRegistry, PendingOperation, StatementKind,
+};
+use rand::{seq::SliceRandom, Rng};
+use rusqlite::types::Value;
+
+use crate::difficulty::Difficulty;
+use crate::game::{headless, GameContext, GameResult};
+use crate::results::ResultSet;
+use crate::rows::RowData;
+use crate::session::{self, say};
+use crate::{bitrot, drift, fire, leaderboard, rows, trash, where_sql};
+
+pub fn register_builtin_effects(registry: &mut EffectRegistry) {
+    registry.register(Box::new(GamingTime));
+    registry.register(Box::new(DatabaseOnFire));
+    registry.register(Box::new(DataInTrash));
+    registry.register(Box::new(NothingHappened));
+    registry.register(Box::new(BitRot));
+    registry.register(Box::new(ShuffleRows));
+    registry.register(Box::new(SwapColumns));
+    registry.register(Box::new(InventRows));
+    registry.register(Box::new(HideRows));
+    registry.register(Box::new(SchemaDrift));
+}
+
+pub struct GamingTime;
+
+// Rows a loss costs for every item missed, before difficulty
+const ROWS_PER_MISS: u32 = 2;
+
+impl ChaosEffect for GamingTime {
+    fn name(&self) -> &str {
+        "gaming_time"
+    }
+
+    fn apply(
+        &self,
+        ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        let Some(game) = ctx.games.pick(ctx.rng) else {
+            say!("It's time to play a classic! Except there are no games, so off you go.");
+            return Ok(EffectOutcome::Proceed);
+        };
+        say!("It's time to play a classic! Today's game: {}", game.name());
+        // Keep the game on the database's seed, whether it's actually played or replayed
+        let game_ctx = GameContext {
+            conn: ctx.conn,
+            seed: ctx.rng.gen(),
+            difficulty: ctx.difficulty,
+            operation,
+        };
+        // Why a game wouldn't start is recorded too, so a replay says the same thing
+        let played = session::game_result(
+            game_ctx.seed,
+            || ctx.games.play(game, &game_ctx).map_err(|e| e.to_string()),
+            |tape| headless::replay(game, &game_ctx, tape).map_err(|e| e.to_string()),
+        );
+        // No game, no price: a missing terminal isn't the player giving up
+        let result = match played {
+            Ok(result) => result,
+            Err(reason) => {
+                say!("The game wouldn't start ({}), so off you go.", reason);
+                return Ok(EffectOutcome::Proceed);
+            }
+        };
+        // Win or lose, it goes on the leaderboard
+        if let Err(e) =
+            leaderboard::record(ctx.conn, ctx.player, game.name(), &result, &operation.sql)
+        {
+            say!("The leaderboard dropped your score: {}", e);
+        }
+
+        // Some games charge for a win too
+        let owed = game
+            .rows_owed(&result, ctx.difficulty)
+            .unwrap_or(if result.won {
+                0
+            } else {
+                rows_owed(&result, ctx.difficulty)
+            });
+        // The game is over before the table is looked at, so only the rows it cost are undone
+        let lost = ctx.undoable(&operation.table_name, |ctx| {
+            lose_rows(ctx, &operation.table_name, owed as usize)
+        })?;
+        ctx.rows_affected = lost.len();
+
+        if result.won {
+            say!("You won! Your statement gets to go through.");
+        }
+        if !lost.is_empty() {
+            say!(
+                "You scored {} and missed {}. That cost you {} row(s) of '{}':",
+                result.score,
+                result.misses,
+                lost.len(),
+                operation.table_name
+            );
+            for data in &lost {
+                say!("  {}", serde_json::Value::Object(data.clone()));
+            }
+        }
+
+        if result.won {
+            if fire::extinguish(ctx.conn, None)? > 0 {
+                say!("You put out every fire while you were at it! 🧯");
+            }
+            return Ok(EffectOutcome::Proceed);
+        }
+        if lost.is_empty() {
+            return Ok(EffectOutcome::Abort(format!(
+                "You lost the game! You scored {} and missed {}, which luckily cost you nothing this time.",
+                result.score, result.misses
+            )));
+        }
+
+        Ok(EffectOutcome::Abort(
+            "You lost the game! You also lost some PRECIOUS data as well!".to_string(),
+        ))
+    }
+}
+
+// Every miss costs rows, every point scored saves one
+fn rows_owed(result: &GameResult, difficulty: Difficulty) -> u32 {
+    (result.misses * difficulty.scale(ROWS_PER_MISS).max(1)).saturating_sub(result.score)
+}
+
+// Deletes up to `count` random rows from the table. A table that doesn't exist (yet) has
+// nothing to lose, so no other table pays for it. Returns the rows lost.
+fn lose_rows(
+    ctx: &mut ChaosContext,
+    table_name: &str,
+    count: usize,
+) -> Result<Vec<RowData>, Box<dyn std::error::Error>> {
+    if count == 0 || !rows::has_table(ctx.conn, table_name)? {
+        return Ok(Vec::new());
+    }
+
+    let candidates = rows::fetch_rows(ctx.conn, table_name, "1 = 1")?;
+    let mut lost = Vec::new();
+    for (rowid, data) in candidates.choose_multiple(ctx.rng, count) {
+        ctx.conn.execute(
+            &format!("DELETE FROM {} WHERE rowid = ?1", table_name),
+            [rowid],
+        )?;
+        bitrot::forget_rows(ctx.conn, table_name, &[*rowid])?;
+        lost.push(data.clone());
+    }
+
+    Ok(lost)
+}
+
+pub struct DatabaseOnFire;
+
+impl ChaosEffect for DatabaseOnFire {
+    fn name(&self) -> &str {
+        "database_on_fire"
+    }
+
+    fn apply(
+        &self,
+        ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        // A table that doesn't exist yet can't burn, so something else catches instead
+        let target = rows::pick_table(ctx.conn, ctx.rng, &operation.table_name)?;
+
+        let message = match target {
+            Some(table_name) => {
+                ctx.undoable(&table_name, |ctx| Ok(fire::ignite(ctx.conn, &table_name)?))?;
+                format!(
+                    "Oh no, the database is on fire! 🔥 '{}' caught fire. EXTINGUISH it before it spreads!",
+                    table_name
+                )
+            }
+            None => "Oh no, the database is on fire! 🔥".to_string(),
+        };
+
+        Ok(EffectOutcome::Abort(message))
+    }
+}
+
+pub struct DataInTrash;
+
+impl ChaosEffect for DataInTrash {
+    fn name(&self) -> &str {
+        "data_in_trash"
+    }
+
+    fn apply(
+        &self,
+        ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        let message = match operation.kind {
+            StatementKind::Create => {
+                "Oops, I dropped your data in the trash! Luckily there wasn't any yet.".to_string()
+            }
+            StatementKind::Insert => {
+                let id = ctx.undoable(&operation.table_name, |ctx| {
+                    trash::trash_values(ctx.conn, &operation.table_name, &operation.values)
+                })?;
+                ctx.rows_affected = 1;
+                format!(
+                    "Oops, I dropped your data in the trash! I think I can recover it? (RESTORE {})",
+                    id
+                )
+            }
+            _ => {
+                let trashed = ctx.undoable(&operation.table_name, |ctx| {
+                    trash::trash_matching_rows(
+                        ctx.conn,
+                        &operation.table_name,
+                        &where_sql(&operation.where_clauses),
+                        ctx.difficulty.scale(100),
+                    )
+                })?;
+                ctx.rows_affected = trashed;
+                format!(
+                    "Oops, I dropped {} row(s) of '{}' in the trash! I think I can recover them? (TRASH)",
+                    trashed, operation.table_name
+                )
+            }
+        };
+
+        Ok(EffectOutcome::Abort(message))
+    }
+}
+
+pub struct NothingHappened;
+
+impl ChaosEffect for NothingHappened {
+    fn name(&self) -> &str {
+        "nothing_happened"
+    }
+
+    fn apply(
+        &self,
+        _ctx: &mut ChaosContext,
+        _operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        Ok(EffectOutcome::Abort(
+            "Nah, nothing happened. I'm feelin a little QUIRKY today".to_string(),
+        ))
+    }
+}
+
+pub struct BitRot;
+
+impl ChaosEffect for BitRot {
+    fn name(&self) -> &str {
+        "bit_rot"
+    }
+
+    fn apply(
+        &self,
+        ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        // Rot doesn't announce itself. The statement goes through and nobody is any the wiser,
+        // until someone runs SCRUB.
+        let target = rows::pick_table(ctx.conn, ctx.rng, &operation.table_name)?;
+        if let Some(table_name) = target {
+            let count = ctx.difficulty.scale(2).max(1);
+            ctx.rows_affected = ctx.undoable(&table_name, |ctx| {
+                bitrot::rot(ctx.conn, ctx.rng, &table_name, count)
+            })?;
+        }
+
+        Ok(EffectOutcome::Proceed)
+    }
+}
+
+// The lying effects below let the statement run and rewrite its rows afterwards. Only SELECTs
+// have rows to lie about, anything else goes through untouched.
+fn lie_about(operation: &mut PendingOperation, effect: &str) -> EffectOutcome {
+    if operation.kind == StatementKind::Select {
+        operation.lie = Some(effect.to_string());
+    }
+    EffectOutcome::Proceed
+}
+
+pub struct ShuffleRows;
+
+impl ChaosEffect for ShuffleRows {
+    fn name(&self) -> &str {
+        "shuffle_rows"
+    }
+
+    fn apply(
+        &self,
+        _ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        Ok(lie_about(operation, self.name()))
+    }
+
+    fn distort(&self, ctx: &mut ChaosContext, result: &mut ResultSet) -> Option<String> {
+        if result.rows.len() < 2 {
+            return None;
+        }
+
+        result.rows.shuffle(ctx.rng);
+        Some("shuffled the rows".to_string())
+    }
+}
+
+pub struct SwapColumns;
+
+impl ChaosEffect for SwapColumns {
+    fn name(&self) -> &str {
+        "swap_columns"
+    }
+
+    fn apply(
+        &self,
+        _ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        Ok(lie_about(operation, self.name()))
+    }
+
+    fn distort(&self, ctx: &mut ChaosContext, result: &mut ResultSet) -> Option<String> {
+        if result.columns.len() < 2 || result.rows.is_empty() {
+            return None;
+        }
+
+        // The headers stay put, only the values trade places
+        let picked: Vec<usize> =
+            rand::seq::index::sample(ctx.rng, result.columns.len(), 2).into_vec();
+        let (a, b) = (picked[0], picked[1]);
+        for row in &mut result.rows {
+            row.swap(a, b);
+        }
+        Some(format!(
+            "swapped the values of {} and {}",
+            result.columns[a], result.columns[b]
+        ))
+    }
+}
+
+pub struct InventRows;
+
+impl ChaosEffect for InventRows {
+    fn name(&self) -> &str {
+        "invent_rows"
+    }
+
+    fn apply(
+        &self,
+        _ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        Ok(lie_about(operation, self.name()))
+    }
+
+    fn distort(&self, ctx: &mut ChaosContext, result: &mut ResultSet) -> Option<String> {
+        if result.rows.is_empty() {
+            return None;
+        }
+
+        // The best lies are made of the truth: every value is borrowed from a real row
+        let count = ctx.difficulty.scale(2).max(1) as usize;
+        for _ in 0..count {
+            let invented: Vec<Value> = (0..result.columns.len())
+                .map(|column| {
+                    let donor = result.rows.choose(ctx.rng).expect("rows aren't empty");
+                    donor[column].clone()
+                })
+                .collect();
+            let position = ctx.rng.gen_range(0..=result.rows.len());
+            result.rows.insert(position, invented);
+        }
+        Some(format!("made up {} row(s)", count))
+    }
+}
+
+pub struct HideRows;
+
+impl ChaosEffect for HideRows {
+    fn name(&self) -> &str {
+        "hide_rows"
+    }
+
+    fn apply(
+        &self,
+        _ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        Ok(lie_about(operation, self.name()))
+    }
+
+    fn distort(&self, ctx: &mut ChaosContext, result: &mut ResultSet) -> Option<String> {
+        if result.rows.is_empty() {
+            return None;
+        }
+
+        let count = (ctx.difficulty.scale(1).max(1) as usize).min(result.rows.len());
+        for _ in 0..count {
+            let hidden = ctx.rng.gen_range(0..result.rows.len());
+            result.rows.remove(hidden);
+        }
+        Some(format!("hid {} row(s)", count))
+    }
+}
+
+pub struct SchemaDrift;
+
+impl ChaosEffect for SchemaDrift {
+    fn name(&self) -> &str {
+        "schema_drift"
+    }
+
+    fn apply(
+        &self,
+        ctx: &mut ChaosContext,
+        operation: &mut PendingOperation,
+    ) -> Result<EffectOutcome, Box<dyn std::error::Error>> {
+        let Some(table_name) = rows::pick_table(ctx.conn, ctx.rng, &operation.table_name)? else {
+            return Ok(EffectOutcome::Proceed);
+        };
+
+        // This statement was already on its way, so it gets the new names. The next ones
+        // won't be so lucky.
+        let renamed_table = ctx.undoable(&table_name, |ctx| {
+            drift::drift(ctx.conn, ctx.rng, &table_name)
+        })?;
+        if let Some(new_name) = renamed_table.filter(|_| table_name == operation.table_name) {
+            operation.table_name = new_name;
+        }
+        if operation.kind != StatementKind::Create {
+            drift::resolve_columns(ctx.conn, operation)?;
+        }
+
+        Ok(EffectOutcome::Proceed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameRegistry;
+    use crate::rows::fixtures;
+    use crate::snapshot::Changes;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn lost_game(score: u32, misses: u32) -> GameResult {
+        GameResult {
+            won: false,
+            score,
+            misses,
+            ..GameResult::forfeit()
+        }
+    }
+
+    #[test]
+    fn misses_cost_rows_and_points_save_them() {
+        assert_eq!(rows_owed(&lost_game(0, 3), Difficulty::Normal), 6);
+        assert_eq!(rows_owed(&lost_game(4, 3), Difficulty::Normal), 2);
+        assert_eq!(rows_owed(&lost_game(9, 3), Difficulty::Normal), 0);
+    }
+
+    #[test]
+    fn misses_cost_more_on_harder_difficulties() {
+        assert_eq!(rows_owed(&lost_game(0, 3), Difficulty::Easy), 3);
+        assert_eq!(rows_owed(&lost_game(0, 3), Difficulty::Nightmare), 12);
+    }
+
+    // Only a game that was actually given up is a forfeit. One that wouldn't start costs
+    // nothing at all.
+    #[test]
+    fn giving_up_costs_as_much_as_missing_everything() {
+        assert_eq!(
+            rows_owed(&GameResult::forfeit(), Difficulty::Normal),
+            rows_owed(&lost_game(0, 3), Difficulty::Normal)
+        );
+    }
+
+    #[test]
+    fn losing_a_game_over_a_new_table_costs_no_other_table() {
+        let conn = fixtures::pets();
+        let mut rng = StdRng::seed_from_u64(0);
+        let games = GameRegistry::default();
+        let mut ctx = ChaosContext {
+            conn: &conn,
+            rng: &mut rng,
+            difficulty: Difficulty::Normal,
+            games: &games,
+            player: "ana",
+            rows_affected: 0,
+            changes: Changes::default(),
+        };
+
+        assert!(lose_rows(&mut ctx, "owners", 6).unwrap().is_empty());
+        assert_eq!(rows::fetch_rows(&conn, "pets", "1 = 1").unwrap().len(), 3);
+    }
+}
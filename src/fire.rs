@@ -0,0 +1,205 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use rusqlite::Connection;
+
+use crate::bitrot;
+use crate::difficulty::Difficulty;
+use crate::rows;
+
+pub const FIRE_TABLE: &str = "_wacky_fire";
+
+// Chance, in percent, that each burning table sets another table alight on every tick
+const SPREAD_CHANCE: u32 = 25;
+// Every point of intensity is another 10% chance per tick of a row going up in smoke
+const MAX_INTENSITY: u32 = 5;
+
+pub enum FireEvent {
+    Spread { from: String, to: String },
+    RowBurned { table_name: String },
+}
+
+pub fn ensure_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                table_name TEXT PRIMARY KEY,
+                intensity INTEGER NOT NULL DEFAULT 1,
+                caught_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            FIRE_TABLE
+        ),
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn ignite(conn: &Connection, table_name: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO {} (table_name) VALUES (?1)",
+            FIRE_TABLE
+        ),
+        [table_name],
+    )?;
+
+    Ok(())
+}
+
+pub fn burning_tables(conn: &Connection) -> rusqlite::Result<Vec<(String, u32)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT table_name, intensity FROM {} ORDER BY caught_at, table_name",
+        FIRE_TABLE
+    ))?;
+    let burning = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect();
+
+    burning
+}
+
+pub fn is_burning(conn: &Connection, table_name: &str) -> rusqlite::Result<bool> {
+    let count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {} WHERE table_name = ?1", FIRE_TABLE),
+        [table_name],
+        |row| row.get(0),
+    )?;
+
+    Ok(count > 0)
+}
+
+// Burning tables are read-only until someone puts them out
+pub fn guard_write(conn: &Connection, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if is_burning(conn, table_name)? {
+        return Err(format!(
+            "'{}' is on fire! 🔥 It's read-only until someone runs EXTINGUISH.",
+            table_name
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+// Fires are part of the chaos, so boring mode walks right past them
+pub fn guard_write_unless_boring(
+    conn: &Connection,
+    table_name: &str,
+    difficulty: Difficulty,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if difficulty.is_boring() {
+        return Ok(());
+    }
+
+    guard_write(conn, table_name)
+}
+
+// Puts out one table, or every table when none is given. Returns how many fires went out.
+pub fn extinguish(conn: &Connection, table_name: Option<&str>) -> rusqlite::Result<usize> {
+    match table_name {
+        Some(table_name) => conn.execute(
+            &format!("DELETE FROM {} WHERE table_name = ?1", FIRE_TABLE),
+            [table_name],
+        ),
+        None => conn.execute(&format!("DELETE FROM {}", FIRE_TABLE), []),
+    }
+}
+
+pub fn rename_table(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "UPDATE {} SET table_name = ?2 WHERE table_name = ?1",
+            FIRE_TABLE
+        ),
+        [from, to],
+    )?;
+
+    Ok(())
+}
+
+// Lets the fire grow for one tick: it gets hotter, eats rows and jumps to other tables
+pub fn spread(
+    conn: &Connection,
+    rng: &mut StdRng,
+    difficulty: Difficulty,
+) -> Result<Vec<FireEvent>, Box<dyn std::error::Error>> {
+    let burning = burning_tables(conn)?;
+    if burning.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Tables can be dropped while they burn, the fire goes out with them
+    let user_tables = rows::user_tables(conn)?;
+    let mut events = Vec::new();
+    for (table_name, intensity) in burning {
+        if !user_tables.contains(&table_name) {
+            extinguish(conn, Some(&table_name))?;
+            continue;
+        }
+
+        // Even the smallest fire on easy gets a chance to take a row
+        if rng.gen_range(0..10) < difficulty.scale(intensity).max(1) {
+            let rowids = rows::fetch_rows(conn, &table_name, "1 = 1")?
+                .into_iter()
+                .map(|(rowid, _)| rowid)
+                .collect::<Vec<i64>>();
+            if let Some(rowid) = rowids.choose(rng) {
+                conn.execute(
+                    &format!("DELETE FROM {} WHERE rowid = ?1", table_name),
+                    [rowid],
+                )?;
+                bitrot::forget_rows(conn, &table_name, &[*rowid])?;
+                events.push(FireEvent::RowBurned {
+                    table_name: table_name.clone(),
+                });
+            }
+        }
+
+        conn.execute(
+            &format!(
+                "UPDATE {} SET intensity = MIN(intensity + 1, ?1) WHERE table_name = ?2",
+                FIRE_TABLE
+            ),
+            rusqlite::params![MAX_INTENSITY, table_name],
+        )?;
+
+        if rng.gen_range(0..100) < difficulty.scale(SPREAD_CHANCE) {
+            let mut unburnt = Vec::new();
+            for candidate in &user_tables {
+                if !is_burning(conn, candidate)? {
+                    unburnt.push(candidate);
+                }
+            }
+            if let Some(target) = unburnt.choose(rng) {
+                ignite(conn, target)?;
+                events.push(FireEvent::Spread {
+                    from: table_name.clone(),
+                    to: target.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rows::fixtures;
+    use rand::SeedableRng;
+
+    #[test]
+    fn a_new_fire_on_easy_still_burns() {
+        let burned = (0..50).any(|seed| {
+            let conn = fixtures::pets();
+            ignite(&conn, "pets").unwrap();
+            let mut rng = StdRng::seed_from_u64(seed);
+            spread(&conn, &mut rng, Difficulty::Easy)
+                .unwrap()
+                .iter()
+                .any(|event| matches!(event, FireEvent::RowBurned { .. }))
+        });
+
+        assert!(burned);
+    }
+}
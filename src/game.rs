@@ -1,14 +1,418 @@
 use crossterm::{
     cursor,
-    event::{self, KeyCode, KeyEvent},
-    execute,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
     terminal::{self, ClearType},
 };
-use rand::Rng;
-use std::io::{self, Write};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::thread;
 use std::time::{Duration, Instant};
 
-#[derive(Clone, Copy)]
+use crate::background;
+use crate::chaos::{PendingOperation, StatementKind};
+use crate::difficulty::Difficulty;
+use crate::rows;
+use crate::trivia::Trivia;
+
+// Giving up, or not being able to play at all, counts as this many misses whatever the game
+pub const FORFEIT_MISSES: u32 = 3;
+// How long the final score stays up before the terminal goes back to the prompt
+const RESULT_SCREEN: Duration = Duration::from_millis(1500);
+
+// How often items fall a row, on normal difficulty, and how often a new one shows up
+const CATCHING_TICK: Duration = Duration::from_millis(100);
+const SPAWN_EVERY: Duration = Duration::from_secs(1);
+const MAX_MISSES: u32 = 3;
+// The score line and the top border sit above the field
+const FIELD_TOP: u16 = 2;
+
+// How often the snake moves, on normal difficulty
+const SNAKE_TICK: Duration = Duration::from_millis(150);
+const SNAKE_TIME: Duration = Duration::from_secs(45);
+// Snacks to eat to win
+const SNAKE_TARGET: u32 = 5;
+// The biggest and smallest field the snake gets, whatever the size of the terminal
+const SNAKE_FIELD: (u16, u16) = (40, 15);
+const MIN_SNAKE_FIELD: u16 = 5;
+// What the snake eats when there's no INSERT to make snacks from
+const CRUMB: &str = "crumb";
+
+// How a game went, which is what decides what it costs
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameResult {
+    pub won: bool,
+    // Points scored, each of which saves a row on a loss
+    pub score: u32,
+    // Mistakes made, each of which costs rows on a loss
+    pub misses: u32,
+    // How long the game went on for. Sessions recorded before it was kept have it as zero.
+    #[serde(default)]
+    pub duration: Duration,
+}
+
+impl GameResult {
+    pub fn forfeit() -> Self {
+        Self {
+            won: false,
+            score: 0,
+            misses: FORFEIT_MISSES,
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+// Where a game's key presses come from
+pub trait Input {
+    // The next key pressed within the timeout, if there was one
+    fn poll_key(&mut self, timeout: Duration) -> io::Result<Option<KeyEvent>>;
+}
+
+// Where a game's time comes from
+pub trait Clock {
+    // How long the clock has been running
+    fn now(&self) -> Duration;
+}
+
+// Where a game's frames go
+pub trait Renderer {
+    fn render(&mut self, frame: &Frame) -> io::Result<()>;
+
+    // How much room there is for a frame, in columns and lines
+    fn size(&self) -> (u16, u16);
+}
+
+// Everything a game talks to the outside world through
+pub struct GameIo<'a> {
+    pub input: &'a mut dyn Input,
+    pub clock: &'a dyn Clock,
+    pub renderer: &'a mut dyn Renderer,
+}
+
+// One wait for a key, as the game saw it: when it was over, since the game started, and the
+// key that ended it, if any
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Poll {
+    pub at: Duration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<KeyEvent>,
+}
+
+// Everything a game took in while it was played, so it can be played again headless
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Tape {
+    // How much room the terminal had, which some games size their field by
+    pub size: (u16, u16),
+    pub polls: Vec<Poll>,
+}
+
+// Plays a game on `io` and keeps a tape of it. The game only sees its clock move when a wait
+// for a key is over, so the keys and when each wait ended are all it takes to play it again
+// exactly, with `headless::replay`.
+pub fn play_on_tape(
+    game: &dyn MiniGame,
+    io: &mut GameIo,
+    ctx: &GameContext,
+) -> io::Result<(GameResult, Tape)> {
+    let clock = headless::ManualClock::default();
+    let mut recorder = Recorder {
+        input: &mut *io.input,
+        time: io.clock,
+        start: io.clock.now(),
+        clock: clock.clone(),
+        tape: Tape {
+            size: io.renderer.size(),
+            polls: Vec::new(),
+        },
+    };
+
+    let result = game.play(
+        &mut GameIo {
+            input: &mut recorder,
+            clock: &clock,
+            renderer: &mut *io.renderer,
+        },
+        ctx,
+    )?;
+    Ok((result, recorder.tape))
+}
+
+// Writes down every wait for a key as it ends, and moves the game's clock on to then
+struct Recorder<'a> {
+    input: &'a mut dyn Input,
+    time: &'a dyn Clock,
+    start: Duration,
+    clock: headless::ManualClock,
+    tape: Tape,
+}
+
+impl Input for Recorder<'_> {
+    fn poll_key(&mut self, timeout: Duration) -> io::Result<Option<KeyEvent>> {
+        let key = self.input.poll_key(timeout)?;
+        let at = self.time.now() - self.start;
+        self.clock.advance_to(at);
+        self.tape.polls.push(Poll { at, key });
+        Ok(key)
+    }
+}
+
+// One screenful of a game, as text
+#[derive(Default)]
+pub struct Frame {
+    lines: Vec<Vec<char>>,
+}
+
+impl Frame {
+    // Writes the text starting at the given column and line, growing the frame to fit
+    pub fn put(&mut self, x: u16, y: u16, text: &str) {
+        let (x, y) = (x as usize, y as usize);
+        if self.lines.len() <= y {
+            self.lines.resize(y + 1, Vec::new());
+        }
+        let line = &mut self.lines[y];
+        for (i, c) in text.chars().enumerate() {
+            if line.len() <= x + i {
+                line.resize(x + i + 1, ' ');
+            }
+            line[x + i] = c;
+        }
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.lines
+            .iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+// How far along a game that plays on run_loop is, kept the same way whatever the game
+#[derive(Default)]
+pub struct Progress {
+    pub over: bool,
+    // Set when the player gives up with Esc, q or Ctrl+C
+    pub forfeited: bool,
+    // How long the game has been going, as of the last time run_loop checked the clock
+    pub elapsed: Duration,
+}
+
+// A game that plays out over time, one key and one step at a time. run_loop does the rest.
+pub trait GameLoop {
+    fn progress(&self) -> &Progress;
+
+    fn progress_mut(&mut self) -> &mut Progress;
+
+    // How long to wait for a key before the game has to move on by itself, with `elapsed`
+    // the time since it started
+    fn wait(&self, elapsed: Duration) -> Duration;
+
+    // Any key but the ones that give up
+    fn handle_key(&mut self, key: KeyEvent);
+
+    // Makes the player pay for giving up, which should never be cheaper than playing on
+    fn charge_forfeit(&mut self);
+
+    // Catches the game up to `elapsed` since it started
+    fn advance(&mut self, elapsed: Duration);
+
+    fn frame(&self) -> Frame;
+
+    // How it went. run_loop fills in how long it took.
+    fn result(&self) -> GameResult;
+
+    // Gives up on Esc, q or Ctrl+C, whatever the game, and leaves any other key to it
+    fn press(&mut self, key: KeyEvent) {
+        if !is_forfeit_key(&key) {
+            self.handle_key(key);
+            return;
+        }
+
+        let progress = self.progress_mut();
+        progress.forfeited = true;
+        progress.over = true;
+        self.charge_forfeit();
+    }
+}
+
+// Shows the game and feeds it keys and time until the player wins, loses or gives up
+pub fn run_loop(game: &mut impl GameLoop, io: &mut GameIo) -> io::Result<GameResult> {
+    let start = io.clock.now();
+    io.renderer.render(&game.frame())?;
+
+    while !game.progress().over {
+        let timeout = game.wait(io.clock.now() - start);
+        if let Some(key) = io.input.poll_key(timeout)? {
+            game.press(key);
+        }
+
+        let elapsed = io.clock.now() - start;
+        game.progress_mut().elapsed = elapsed;
+        game.advance(elapsed);
+        io.renderer.render(&game.frame())?;
+    }
+
+    Ok(GameResult {
+        duration: io.clock.now() - start,
+        ..game.result()
+    })
+}
+
+// Esc, q and Ctrl+C give up, whatever the game
+fn is_forfeit_key(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        KeyCode::Esc | KeyCode::Char('q') => true,
+        _ => false,
+    }
+}
+
+// What every game says to a player who gave up
+pub const GAVE_UP: &str = "You gave up! That counts as a loss.";
+
+// A field of the given size below the score line, with a wall all around
+fn draw_field(frame: &mut Frame, width: u16, height: u16) {
+    let border = format!("+{}+", "-".repeat(width as usize));
+    frame.put(0, FIELD_TOP - 1, &border);
+    for y in 0..height {
+        frame.put(0, FIELD_TOP + y, "|");
+        frame.put(width + 1, FIELD_TOP + y, "|");
+    }
+    frame.put(0, FIELD_TOP + height, &border);
+}
+
+// What a game knows about where it's being played
+pub struct GameContext<'a> {
+    // For games about the database itself. Games look, they don't touch.
+    pub conn: &'a Connection,
+    // Everything random comes from the seed, so a replay plays the same game
+    pub seed: u64,
+    // Harder difficulties should make for a harder game
+    pub difficulty: Difficulty,
+    // The statement the game is being played for
+    pub operation: &'a PendingOperation,
+}
+
+// A game GamingTime can make the user play. Games only ever see a `GameIo`, so the same game
+// plays on a real terminal (see `GameRegistry::play`) or headless in tests.
+pub trait MiniGame {
+    // The name players pick it by, e.g. "catching"
+    fn name(&self) -> &str;
+
+    // Plays one round and says how it went
+    fn play(&self, io: &mut GameIo, ctx: &GameContext) -> io::Result<GameResult>;
+
+    // What the game costs, in rows, for games with a price of their own. None leaves it to
+    // GamingTime, where a win is free and a loss costs rows for every miss.
+    fn rows_owed(&self, _result: &GameResult, _difficulty: Difficulty) -> Option<u32> {
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct GameRegistry {
+    games: BTreeMap<String, Box<dyn MiniGame>>,
+    // The game to play every time, instead of one picked at random
+    preferred: Option<String>,
+}
+
+impl GameRegistry {
+    // Registering a game with a name that is already taken replaces the old one
+    pub fn register(&mut self, game: Box<dyn MiniGame>) {
+        self.games.insert(game.name().to_string(), game);
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.games.keys().map(|name| name.as_str())
+    }
+
+    pub fn preferred(&self) -> Option<&str> {
+        self.preferred.as_deref()
+    }
+
+    // None goes back to picking at random
+    pub fn prefer(&mut self, name: Option<&str>) -> Result<(), String> {
+        match name {
+            Some(name) if !self.games.contains_key(name) => {
+                Err(format!("'{}' is not a game I know", name))
+            }
+            _ => {
+                self.preferred = name.map(str::to_string);
+                Ok(())
+            }
+        }
+    }
+
+    // The preferred game, or one at random
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> Option<&dyn MiniGame> {
+        let name = match &self.preferred {
+            Some(name) => name.as_str(),
+            None => *self.names().collect::<Vec<&str>>().choose(rng)?,
+        };
+        self.games.get(name).map(|game| game.as_ref())
+    }
+
+    // Hands the terminal over to the game, and takes it back however the game ends. Comes
+    // back with a tape of the game, too.
+    pub fn play(&self, game: &dyn MiniGame, ctx: &GameContext) -> io::Result<(GameResult, Tape)> {
+        let _quiet = background::pause();
+        let _guard = TerminalGuard::new()?;
+        let stdout = io::stdout();
+        let mut io = GameIo {
+            input: &mut TerminalInput,
+            clock: &SystemClock(Instant::now()),
+            renderer: &mut TerminalRenderer(stdout.lock()),
+        };
+
+        let played = play_on_tape(game, &mut io, ctx)?;
+        thread::sleep(RESULT_SCREEN);
+        Ok(played)
+    }
+}
+
+pub fn register_builtin_games(registry: &mut GameRegistry) {
+    registry.register(Box::new(Catching));
+    registry.register(Box::new(Trivia));
+    registry.register(Box::new(Snake));
+}
+
+// Catch the falling items before too many hit the floor
+pub struct Catching;
+
+impl MiniGame for Catching {
+    fn name(&self) -> &str {
+        "catching"
+    }
+
+    fn play(&self, io: &mut GameIo, ctx: &GameContext) -> io::Result<GameResult> {
+        // Things fall faster the harder it gets
+        let tick = CATCHING_TICK * 100 / ctx.difficulty.severity().max(50);
+        let target_score = 10; // Number of items to catch to win
+        let game_time = Duration::new(30, 0); // Game time limit (30 seconds)
+        let game_rng = StdRng::seed_from_u64(ctx.seed);
+        let mut game = CatchingGame::new(20, 10, target_score, game_time, tick, game_rng); // Initialize with width and height
+
+        run_loop(&mut game, io)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 struct Point {
     x: u16,
     y: u16,
@@ -26,15 +430,96 @@ struct CatchingGame {
     misses: u32,
     width: u16,
     height: u16,
-    game_over: bool,
+    progress: Progress,
     game_time: Duration,
-    start_time: Instant,
     target_score: u32,
-    game_result: Option<String>, // Store the game result
+    // How often items fall a row
+    tick: Duration,
+    // When, since the start, items next fall and the next one shows up
+    next_tick: Duration,
+    next_spawn: Duration,
+    rng: StdRng,
+}
+
+// Puts the terminal in raw mode on an alternate screen for as long as it's alive. Dropping it
+// puts everything back, whether the game ended, errored or panicked.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> io::Result<Self> {
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "there's no terminal to play on",
+            ));
+        }
+
+        terminal::enable_raw_mode()?;
+        // From here on the guard exists, so a failure below still gets cleaned up
+        let guard = TerminalGuard;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Key presses from the terminal
+struct TerminalInput;
+
+impl Input for TerminalInput {
+    fn poll_key(&mut self, timeout: Duration) -> io::Result<Option<KeyEvent>> {
+        if !event::poll(timeout)? {
+            return Ok(None);
+        }
+        match event::read()? {
+            // Some terminals report releases too, only presses count
+            Event::Key(key) if key.kind == KeyEventKind::Press => Ok(Some(key)),
+            _ => Ok(None),
+        }
+    }
+}
+
+// Wall-clock time since the game started
+struct SystemClock(Instant);
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+// Redraws the whole terminal every frame
+struct TerminalRenderer<W: Write>(W);
+
+impl<W: Write> Renderer for TerminalRenderer<W> {
+    fn render(&mut self, frame: &Frame) -> io::Result<()> {
+        queue!(self.0, terminal::Clear(ClearType::All))?;
+        for (y, line) in frame.lines().enumerate() {
+            queue!(self.0, cursor::MoveTo(0, y as u16), Print(line))?;
+        }
+        self.0.flush()
+    }
+
+    fn size(&self) -> (u16, u16) {
+        terminal::size().unwrap_or((80, 24))
+    }
 }
 
 impl CatchingGame {
-    fn new(width: u16, height: u16, target_score: u32, game_time: Duration) -> Self {
+    fn new(
+        width: u16,
+        height: u16,
+        target_score: u32,
+        game_time: Duration,
+        tick: Duration,
+        rng: StdRng,
+    ) -> Self {
         CatchingGame {
             player: Point {
                 x: width / 2,
@@ -45,75 +530,26 @@ impl CatchingGame {
             misses: 0,
             width,
             height,
-            game_over: false,
+            progress: Progress::default(),
             game_time,
-            start_time: Instant::now(),
             target_score,
-            game_result: None, // Initialize with None
+            tick,
+            next_tick: tick,
+            next_spawn: SPAWN_EVERY,
+            rng,
         }
     }
 
-    fn run(&mut self) -> io::Result<Option<String>> {
-        // Change return type to Option<String>
-        terminal::enable_raw_mode()?; // Enter raw mode
-        let stdout = io::stdout();
-        let mut stdout = stdout.lock();
-
-        let mut last_instant = Instant::now();
-        let mut item_timer = Instant::now();
-
-        while !self.game_over {
-            if last_instant.elapsed() > Duration::from_millis(100) {
-                self.update()?;
-                self.draw(&mut stdout)?;
-                last_instant = Instant::now();
-            }
-
-            // Spawn new falling items every second
-            if item_timer.elapsed() > Duration::from_secs(1) {
-                self.spawn_item();
-                item_timer = Instant::now();
-            }
-
-            // Check for user input (to move the player)
-            if event::poll(Duration::from_millis(1))? {
-                if let event::Event::Key(KeyEvent {
-                    code: KeyCode::Left,
-                    ..
-                }) = event::read()?
-                {
-                    if self.player.x > 0 {
-                        self.player.x -= 1;
-                    }
-                } else if let event::Event::Key(KeyEvent {
-                    code: KeyCode::Right,
-                    ..
-                }) = event::read()?
-                {
-                    if self.player.x < self.width - 1 {
-                        self.player.x += 1;
-                    }
-                }
-            }
-        }
-
-        terminal::disable_raw_mode()?; // Exit raw mode
-
-        // Determine game result
-        if self.score >= self.target_score {
-            self.game_result = Some("You Won! Caught enough items!".to_string());
-        } else {
-            self.game_result = Some("Game Over! Missed too many items.".to_string());
-        }
-
-        Ok(self.game_result.clone()) // Return the game result
+    fn won(&self) -> bool {
+        !self.progress.forfeited && self.score >= self.target_score
     }
 
-    fn update(&mut self) -> io::Result<()> {
+    // One tick of the game, with `elapsed` the time since it started
+    fn update(&mut self, elapsed: Duration) {
         // Check if game time has expired
-        if self.start_time.elapsed() >= self.game_time {
-            self.game_over = true;
-            return Ok(());
+        if elapsed >= self.game_time {
+            self.progress.over = true;
+            return;
         }
 
         for item in &mut self.falling_items {
@@ -123,11 +559,6 @@ impl CatchingGame {
             if item.position.y == self.player.y && item.position.x == self.player.x {
                 item.caught = true;
                 self.score += 1;
-
-                // Check for success condition
-                if self.score >= self.target_score {
-                    self.game_over = true; // Success condition met
-                }
             } else if item.position.y >= self.height {
                 self.misses += 1;
                 item.caught = true; // Mark as caught even if missed
@@ -137,62 +568,1053 @@ impl CatchingGame {
         // Remove caught or missed items
         self.falling_items.retain(|item| !item.caught);
 
-        // Check failure condition
-        if self.misses >= 3 {
-            self.game_over = true;
+        if self.score >= self.target_score || self.misses >= MAX_MISSES {
+            self.progress.over = true;
         }
+    }
 
-        Ok(())
+    fn spawn_item(&mut self) {
+        let x_position = self.rng.gen_range(0..self.width);
+        let new_item = FallingItem {
+            position: Point {
+                x: x_position,
+                y: 0,
+            },
+            caught: false,
+        };
+        self.falling_items.push(new_item);
     }
+}
+
+impl GameLoop for CatchingGame {
+    fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    fn progress_mut(&mut self) -> &mut Progress {
+        &mut self.progress
+    }
+
+    // No longer than until items next fall
+    fn wait(&self, elapsed: Duration) -> Duration {
+        self.next_tick.saturating_sub(elapsed)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Left | KeyCode::Char('a') => self.player.x = self.player.x.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('d') => {
+                self.player.x = (self.player.x + 1).min(self.width - 1)
+            }
+            _ => {}
+        }
+    }
+
+    // Giving up counts as missing everything that was left to miss
+    fn charge_forfeit(&mut self) {
+        self.misses = self.misses.max(MAX_MISSES);
+    }
+
+    fn advance(&mut self, elapsed: Duration) {
+        if elapsed >= self.next_spawn {
+            self.spawn_item();
+            self.next_spawn = elapsed + SPAWN_EVERY;
+        }
+        if elapsed >= self.next_tick {
+            self.update(elapsed);
+            self.next_tick = elapsed + self.tick;
+        }
+    }
+
+    fn frame(&self) -> Frame {
+        let mut frame = Frame::default();
+
+        // Score line
+        let time_left = self.game_time.saturating_sub(self.progress.elapsed);
+        frame.put(
+            0,
+            0,
+            &format!(
+                "Caught {}/{}  Missed {}/{}  {}s left  (←/→ to move, q to give up)",
+                self.score,
+                self.target_score,
+                self.misses,
+                MAX_MISSES,
+                time_left.as_secs()
+            ),
+        );
+
+        draw_field(&mut frame, self.width, self.height);
 
-    fn draw<W: Write>(&self, stdout: &mut W) -> io::Result<()> {
-        execute!(stdout, terminal::Clear(ClearType::All))?;
-        execute!(stdout, cursor::DisableBlinking)?;
-        // Draw player
-        execute!(stdout, cursor::MoveTo(self.player.x, self.player.y),)?;
         // Draw falling items
         for item in &self.falling_items {
-            execute!(stdout, cursor::MoveTo(item.position.x, item.position.y),)?;
+            frame.put(item.position.x + 1, FIELD_TOP + item.position.y, "*");
         }
-        // Display score and misses
-        execute!(stdout, cursor::MoveTo(0, 0),)?;
+        // Draw player
+        frame.put(self.player.x + 1, FIELD_TOP + self.player.y, "@");
 
-        if self.game_over {
-            execute!(stdout, cursor::MoveTo(0, self.height / 2),)?;
+        if self.progress.over {
+            let message = if self.won() {
+                "You Won! Caught enough items!"
+            } else if self.progress.forfeited {
+                GAVE_UP
+            } else {
+                "Game Over! Missed too many items."
+            };
+            frame.put(0, FIELD_TOP + self.height + 2, message);
         }
 
-        stdout.flush()?;
-        Ok(())
+        frame
     }
 
-    fn spawn_item(&mut self) {
-        let mut rng = rand::thread_rng();
-        let x_position = rng.gen_range(0..self.width);
-        let new_item = FallingItem {
-            position: Point {
-                x: x_position,
-                y: 0,
+    fn result(&self) -> GameResult {
+        GameResult {
+            won: self.won(),
+            score: self.score,
+            misses: self.misses,
+            duration: self.progress.elapsed,
+        }
+    }
+}
+
+// Steer the snake into the snacks without crashing. The snake is the table it's played for:
+// it's as long as the table has rows, and every crash costs one of them.
+pub struct Snake;
+
+impl MiniGame for Snake {
+    fn name(&self) -> &str {
+        "snake"
+    }
+
+    fn play(&self, io: &mut GameIo, ctx: &GameContext) -> io::Result<GameResult> {
+        let rows = table_rows(ctx).map_err(|e| io::Error::other(e.to_string()))?;
+        // Leave room for the score line, the walls and the messages below
+        let (columns, lines) = io.renderer.size();
+        let width = columns.saturating_sub(2).min(SNAKE_FIELD.0);
+        let height = lines.saturating_sub(FIELD_TOP + 4).min(SNAKE_FIELD.1);
+        // The snake eats whatever was about to be inserted
+        let snacks = match ctx.operation.kind {
+            StatementKind::Insert if !ctx.operation.values.is_empty() => {
+                ctx.operation.values.clone()
+            }
+            _ => vec![CRUMB.to_string()],
+        };
+        // The snake is quicker the harder it gets
+        let tick = SNAKE_TICK * 100 / ctx.difficulty.severity().max(50);
+        let mut game = SnakeGame::new(
+            width.max(MIN_SNAKE_FIELD),
+            height.max(MIN_SNAKE_FIELD),
+            rows,
+            snacks,
+            tick,
+            StdRng::seed_from_u64(ctx.seed),
+        );
+
+        run_loop(&mut game, io)
+    }
+
+    // Every crash costs a row, win or lose
+    fn rows_owed(&self, result: &GameResult, _difficulty: Difficulty) -> Option<u32> {
+        Some(result.misses)
+    }
+}
+
+// Rows in the table the game is played for, which might not exist (yet)
+fn table_rows(ctx: &GameContext) -> Result<usize, Box<dyn std::error::Error>> {
+    let table_name = &ctx.operation.table_name;
+    if !rows::user_tables(ctx.conn)?.contains(table_name) {
+        return Ok(0);
+    }
+    let count: i64 =
+        ctx.conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table_name), [], |row| {
+                row.get(0)
+            })?;
+
+    Ok(count as usize)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    // The two ways the snake can turn from here
+    fn turns(self) -> [Direction; 2] {
+        match self {
+            Direction::Up | Direction::Down => [Direction::Left, Direction::Right],
+            Direction::Left | Direction::Right => [Direction::Up, Direction::Down],
+        }
+    }
+
+    // One step this way, unless that's off the field
+    fn step(self, from: Point, width: u16, height: u16) -> Option<Point> {
+        let Point { x, y } = from;
+        let to = match self {
+            Direction::Up => Point {
+                x,
+                y: y.checked_sub(1)?,
             },
-            caught: false,
+            Direction::Down => Point { x, y: y + 1 },
+            Direction::Left => Point {
+                x: x.checked_sub(1)?,
+                y,
+            },
+            Direction::Right => Point { x: x + 1, y },
         };
-        self.falling_items.push(new_item);
+        (to.x < width && to.y < height).then_some(to)
+    }
+}
+
+struct SnakeGame {
+    // Head first
+    body: VecDeque<Point>,
+    direction: Direction,
+    // Where the player last steered, which the snake turns to on its next move
+    steer: Direction,
+    // The snack on the field, and what it's made of
+    food: Option<(Point, String)>,
+    snacks: Vec<String>,
+    eaten: u32,
+    crashes: u32,
+    width: u16,
+    height: u16,
+    progress: Progress,
+    // How often the snake moves, and when, since the start, it next does
+    tick: Duration,
+    next_tick: Duration,
+    // What just happened, shown below the field
+    news: String,
+    rng: StdRng,
+}
+
+impl SnakeGame {
+    // A snake as long as the table has rows, squeezed to fill no more than a third of the
+    // field. It starts out coiled up along the bottom. A table without rows makes no snake,
+    // and the game is a free pass.
+    fn new(
+        width: u16,
+        height: u16,
+        rows: usize,
+        snacks: Vec<String>,
+        tick: Duration,
+        rng: StdRng,
+    ) -> Self {
+        let room = (width as usize * height as usize / 3).max(1);
+        let length = rows.min(room);
+        let rows_per_segment = rows.div_ceil(length.max(1)).max(1);
+
+        // The cells along the bottom, back and forth, a row at a time
+        let coil: Vec<Point> = (0..height)
+            .rev()
+            .flat_map(|y| {
+                let xs: Vec<u16> = if (height - 1 - y) % 2 == 0 {
+                    (0..width).collect()
+                } else {
+                    (0..width).rev().collect()
+                };
+                xs.into_iter().map(move |x| Point { x, y })
+            })
+            .take(length + 1)
+            .collect();
+        let direction = match length.checked_sub(1).map(|tail| (coil[tail], coil[length])) {
+            Some((head, next)) if next.y < head.y => Direction::Up,
+            Some((head, next)) if next.x < head.x => Direction::Left,
+            _ => Direction::Right,
+        };
+
+        let news = if rows == 0 {
+            "There's no table here to make a snake of.".to_string()
+        } else if rows_per_segment > 1 {
+            format!(
+                "Your snake is {} rows long, {} to a segment. Every crash costs one.",
+                rows, rows_per_segment
+            )
+        } else {
+            format!("Your snake is {} row(s) long. Every crash costs one.", rows)
+        };
+
+        let mut game = SnakeGame {
+            body: coil[..length].iter().rev().copied().collect(),
+            direction,
+            steer: direction,
+            food: None,
+            snacks,
+            eaten: 0,
+            crashes: 0,
+            width,
+            height,
+            progress: Progress {
+                over: rows == 0,
+                ..Progress::default()
+            },
+            tick,
+            next_tick: tick,
+            news,
+            rng,
+        };
+        if !game.progress.over {
+            game.spawn_food();
+        }
+        game
+    }
+
+    fn won(&self) -> bool {
+        !self.progress.forfeited && (self.eaten >= SNAKE_TARGET || self.is_free_pass())
+    }
+
+    // No rows, no snake, nothing to play for
+    fn is_free_pass(&self) -> bool {
+        self.body.is_empty() && self.crashes == 0
+    }
+
+    // One move of the snake, with `elapsed` the time since the game started
+    fn update(&mut self, elapsed: Duration) {
+        if elapsed >= SNAKE_TIME {
+            self.progress.over = true;
+            return;
+        }
+
+        // No turning back on itself
+        if self.steer != self.direction.opposite() {
+            self.direction = self.steer;
+        }
+        let next = self
+            .direction
+            .step(self.body[0], self.width, self.height)
+            // The tail moves out of the way as the head moves in
+            .filter(|next| !self.body.iter().rev().skip(1).any(|part| part == next));
+        let Some(next) = next else {
+            self.crash();
+            return;
+        };
+
+        self.body.push_front(next);
+        match self.food.take() {
+            Some((food, snack)) if food == next => {
+                self.eaten += 1;
+                self.news = format!("Yum, {}!", snack);
+                if self.eaten >= SNAKE_TARGET {
+                    self.progress.over = true;
+                } else {
+                    self.spawn_food();
+                }
+            }
+            food => {
+                self.food = food;
+                self.body.pop_back();
+            }
+        }
+    }
+
+    // Hitting a wall or itself costs the snake its tail, and the table a row. The snake
+    // turns away from whatever it hit, if there's anywhere to turn to.
+    fn crash(&mut self) {
+        self.crashes += 1;
+        self.body.pop_back();
+        let Some(&head) = self.body.front() else {
+            self.progress.over = true;
+            return;
+        };
+        self.news = "Crash! That's a row gone.".to_string();
+
+        let mut turns = self.direction.turns();
+        turns.shuffle(&mut self.rng);
+        let free = turns.into_iter().find(|turn| {
+            turn.step(head, self.width, self.height)
+                .is_some_and(|to| !self.body.contains(&to))
+        });
+        if let Some(turn) = free {
+            self.direction = turn;
+            self.steer = turn;
+        }
+    }
+
+    // Puts the next snack somewhere the snake isn't
+    fn spawn_food(&mut self) {
+        let free: Vec<Point> = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Point { x, y }))
+            .filter(|cell| !self.body.contains(cell))
+            .collect();
+        let snack = self.snacks[self.eaten as usize % self.snacks.len()].clone();
+        self.food = free.choose(&mut self.rng).map(|&cell| (cell, snack));
+    }
+}
+
+impl GameLoop for SnakeGame {
+    fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    fn progress_mut(&mut self) -> &mut Progress {
+        &mut self.progress
+    }
+
+    // No longer than until the snake's next move
+    fn wait(&self, elapsed: Duration) -> Duration {
+        self.next_tick.saturating_sub(elapsed)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('w') => self.steer = Direction::Up,
+            KeyCode::Down | KeyCode::Char('s') => self.steer = Direction::Down,
+            KeyCode::Left | KeyCode::Char('a') => self.steer = Direction::Left,
+            KeyCode::Right | KeyCode::Char('d') => self.steer = Direction::Right,
+            _ => {}
+        }
+    }
+
+    // Giving up costs at least as much as losing would
+    fn charge_forfeit(&mut self) {
+        self.crashes = self.crashes.max(FORFEIT_MISSES);
+    }
+
+    fn advance(&mut self, elapsed: Duration) {
+        if elapsed >= self.next_tick {
+            self.update(elapsed);
+            self.next_tick = elapsed + self.tick;
+        }
+    }
+
+    fn frame(&self) -> Frame {
+        let mut frame = Frame::default();
+
+        // Score line
+        let time_left = SNAKE_TIME.saturating_sub(self.progress.elapsed);
+        frame.put(
+            0,
+            0,
+            &format!(
+                "Snacks {}/{}  Crashes {}  {}s left  (arrows or wasd to steer, q to give up)",
+                self.eaten,
+                SNAKE_TARGET,
+                self.crashes,
+                time_left.as_secs()
+            ),
+        );
+
+        draw_field(&mut frame, self.width, self.height);
+
+        // The snack shows as the first letter or digit of what it's made of
+        if let Some((food, snack)) = &self.food {
+            let letter = snack
+                .chars()
+                .find(|c| c.is_ascii_alphanumeric())
+                .unwrap_or('*');
+            frame.put(food.x + 1, FIELD_TOP + food.y, &letter.to_string());
+        }
+        for (i, part) in self.body.iter().enumerate() {
+            let segment = if i == 0 { "@" } else { "o" };
+            frame.put(part.x + 1, FIELD_TOP + part.y, segment);
+        }
+
+        frame.put(0, FIELD_TOP + self.height + 1, &self.news);
+        if self.progress.over {
+            let message = if self.is_free_pass() && !self.progress.forfeited {
+                "Free pass! No rows, no snake."
+            } else if self.won() {
+                "You Won! That snake is well fed."
+            } else if self.progress.forfeited {
+                GAVE_UP
+            } else if self.body.is_empty() {
+                "Game Over! There's no snake left."
+            } else {
+                "Game Over! Time's up, and the snake is still hungry."
+            };
+            frame.put(0, FIELD_TOP + self.height + 3, message);
+        }
+
+        frame
+    }
+
+    fn result(&self) -> GameResult {
+        GameResult {
+            won: self.won(),
+            score: self.eaten,
+            misses: self.crashes,
+            duration: self.progress.elapsed,
+        }
+    }
+}
+
+// Stand-ins for the terminal, so games can be played start to finish without one: in tests,
+// and again from their tape when a session is replayed
+pub mod headless {
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use super::*;
+
+    // Only moves when something waits on it
+    #[derive(Clone, Default)]
+    pub struct ManualClock(Rc<Cell<Duration>>);
+
+    impl ManualClock {
+        pub fn advance_to(&self, to: Duration) {
+            self.0.set(self.0.get().max(to));
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+    }
+
+    // Keys pressed at set times, in milliseconds. Waiting for a key moves the clock on, to the
+    // next key press or to the end of the wait.
+    #[cfg(test)]
+    pub struct ScriptedInput {
+        clock: ManualClock,
+        keys: VecDeque<(Duration, KeyCode)>,
+    }
+
+    #[cfg(test)]
+    impl ScriptedInput {
+        pub fn new(clock: &ManualClock, keys: impl IntoIterator<Item = (u64, KeyCode)>) -> Self {
+            Self {
+                clock: clock.clone(),
+                keys: keys
+                    .into_iter()
+                    .map(|(ms, code)| (Duration::from_millis(ms), code))
+                    .collect(),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    impl Input for ScriptedInput {
+        fn poll_key(&mut self, timeout: Duration) -> io::Result<Option<KeyEvent>> {
+            let deadline = self.clock.now() + timeout;
+            match self.keys.front() {
+                Some(&(at, code)) if at <= deadline => {
+                    self.keys.pop_front();
+                    self.clock.advance_to(at);
+                    Ok(Some(KeyEvent::new(code, KeyModifiers::NONE)))
+                }
+                _ => {
+                    self.clock.advance_to(deadline);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    // Every wait on the tape ends when, and how, it did when the game was played
+    struct TapeInput {
+        clock: ManualClock,
+        polls: VecDeque<Poll>,
+    }
+
+    impl Input for TapeInput {
+        fn poll_key(&mut self, _timeout: Duration) -> io::Result<Option<KeyEvent>> {
+            let Some(poll) = self.polls.pop_front() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the game went on past the end of its tape",
+                ));
+            };
+            self.clock.advance_to(poll.at);
+            Ok(poll.key)
+        }
+    }
+
+    // Shows nothing, on a terminal the size the tape says
+    struct BlankRenderer((u16, u16));
+
+    impl Renderer for BlankRenderer {
+        fn render(&mut self, _frame: &Frame) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn size(&self) -> (u16, u16) {
+            self.0
+        }
+    }
+
+    // Plays a game again from its tape, without showing it. A game that goes on past the end
+    // of the tape went differently this time, and fails.
+    pub fn replay(game: &dyn MiniGame, ctx: &GameContext, tape: &Tape) -> io::Result<GameResult> {
+        let clock = ManualClock::default();
+        let mut input = TapeInput {
+            clock: clock.clone(),
+            polls: tape.polls.iter().cloned().collect(),
+        };
+        let mut io = GameIo {
+            input: &mut input,
+            clock: &clock,
+            renderer: &mut BlankRenderer(tape.size),
+        };
+
+        game.play(&mut io, ctx)
+    }
+
+    // Keeps every frame, as text
+    #[cfg(test)]
+    #[derive(Default)]
+    pub struct BufferRenderer {
+        pub frames: Vec<String>,
+    }
+
+    #[cfg(test)]
+    impl Renderer for BufferRenderer {
+        fn render(&mut self, frame: &Frame) -> io::Result<()> {
+            self.frames.push(frame.to_string());
+            Ok(())
+        }
+
+        // A terminal of the usual size
+        fn size(&self) -> (u16, u16) {
+            (80, 24)
+        }
+    }
+
+    // Plays a game start to finish. Returns how it went and every frame drawn.
+    #[cfg(test)]
+    pub fn play(
+        game: &dyn MiniGame,
+        ctx: &GameContext,
+        keys: impl IntoIterator<Item = (u64, KeyCode)>,
+    ) -> (GameResult, Vec<String>) {
+        let clock = ManualClock::default();
+        let mut input = ScriptedInput::new(&clock, keys);
+        let mut renderer = BufferRenderer::default();
+        let mut io = GameIo {
+            input: &mut input,
+            clock: &clock,
+            renderer: &mut renderer,
+        };
+
+        let result = game.play(&mut io, ctx).unwrap();
+        (result, renderer.frames)
     }
 }
 
-// To fix
-pub fn play_game() -> io::Result<()> {
-    let target_score = 10; // Number of items to catch to win
-    let game_time = Duration::new(30, 0); // Game time limit (30 seconds)
-    let mut game = CatchingGame::new(20, 10, target_score, game_time); // Initialize with width and height
-                                                                       // let result = game.run()?; // Start the game and get the result
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rows::fixtures;
+
+    fn game() -> CatchingGame {
+        CatchingGame::new(
+            5,
+            4,
+            2,
+            Duration::from_secs(60),
+            CATCHING_TICK,
+            StdRng::seed_from_u64(1),
+        )
+    }
+
+    fn drop_item(game: &mut CatchingGame, x: u16) {
+        game.falling_items.push(FallingItem {
+            position: Point { x, y: 0 },
+            caught: false,
+        });
+    }
+
+    // A round on seed 7, played for the operation
+    fn play_game(
+        game: &dyn MiniGame,
+        conn: &Connection,
+        operation: &PendingOperation,
+        keys: impl IntoIterator<Item = (u64, KeyCode)>,
+    ) -> (GameResult, Vec<String>) {
+        let ctx = GameContext {
+            conn,
+            seed: 7,
+            difficulty: Difficulty::Normal,
+            operation,
+        };
+        headless::play(game, &ctx, keys)
+    }
+
+    // A round of catching, with nothing in the database
+    fn play_catching(keys: impl IntoIterator<Item = (u64, KeyCode)>) -> (GameResult, Vec<String>) {
+        let conn = Connection::open_in_memory().unwrap();
+        let operation = PendingOperation::new(StatementKind::Select, "pets");
+        play_game(&Catching, &conn, &operation, keys)
+    }
+
+    fn snake(rows: usize) -> SnakeGame {
+        SnakeGame::new(
+            5,
+            5,
+            rows,
+            vec!["rex".to_string()],
+            SNAKE_TICK,
+            StdRng::seed_from_u64(1),
+        )
+    }
+
+    fn press(game: &mut CatchingGame, code: KeyCode) {
+        game.press(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    // Lets everything fall the given number of rows
+    fn fall(game: &mut CatchingGame, rows: u32) {
+        for _ in 0..rows {
+            game.update(Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn the_preferred_game_is_always_picked() {
+        let mut registry = GameRegistry::default();
+        register_builtin_games(&mut registry);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        assert!(registry.pick(&mut rng).is_some());
+        assert!(registry.prefer(Some("tetris")).is_err());
+        registry.prefer(Some("trivia")).unwrap();
+        assert_eq!(registry.pick(&mut rng).unwrap().name(), "trivia");
+        registry.prefer(Some("catching")).unwrap();
+        assert_eq!(registry.preferred(), Some("catching"));
+        assert_eq!(registry.pick(&mut rng).unwrap().name(), "catching");
+    }
+
+    #[test]
+    fn an_empty_registry_has_nothing_to_play() {
+        let registry = GameRegistry::default();
+
+        assert!(registry.pick(&mut StdRng::seed_from_u64(1)).is_none());
+    }
+
+    #[test]
+    fn items_spawn_in_the_top_row_where_the_seed_says() {
+        let (mut a, mut b) = (game(), game());
+        for _ in 0..5 {
+            a.spawn_item();
+            b.spawn_item();
+        }
+
+        let positions = |game: &CatchingGame| -> Vec<(u16, u16)> {
+            game.falling_items
+                .iter()
+                .map(|item| (item.position.x, item.position.y))
+                .collect()
+        };
+        assert_eq!(positions(&a), positions(&b));
+        assert!(positions(&a).iter().all(|&(x, y)| x < 5 && y == 0));
+    }
+
+    #[test]
+    fn items_landing_on_the_player_are_caught() {
+        let mut game = game();
+        let x = game.player.x;
+        drop_item(&mut game, x);
+        fall(&mut game, 3);
+
+        assert_eq!(game.score, 1);
+        assert!(game.falling_items.is_empty());
+    }
+
+    #[test]
+    fn enough_catches_win_the_game() {
+        let mut game = game();
+        let x = game.player.x;
+        for _ in 0..2 {
+            drop_item(&mut game, x);
+            fall(&mut game, 3);
+        }
+
+        assert!(game.progress.over);
+        assert!(game.won());
+    }
+
+    #[test]
+    fn too_many_misses_lose_the_game() {
+        let mut game = game();
+        press(&mut game, KeyCode::Right);
+        for _ in 0..MAX_MISSES {
+            drop_item(&mut game, 0);
+            fall(&mut game, 4);
+        }
 
-    // Display the result after the game ends
-    /* if let Some(message) = result {
-        if message.contains("Won") {
-            return Ok(());
+        assert_eq!(game.misses, MAX_MISSES);
+        assert!(game.progress.over);
+        assert!(!game.won());
+    }
+
+    #[test]
+    fn running_out_of_time_ends_the_game() {
+        let mut game = game();
+        game.update(Duration::from_secs(60));
+
+        assert!(game.progress.over);
+        assert!(!game.won());
+    }
+
+    #[test]
+    fn the_player_stays_on_the_field() {
+        let mut game = game();
+        for _ in 0..10 {
+            press(&mut game, KeyCode::Left);
+        }
+        assert_eq!(game.player.x, 0);
+        for _ in 0..10 {
+            press(&mut game, KeyCode::Char('d'));
         }
+        assert_eq!(game.player.x, 4);
     }
 
-    Err(io::Error::new(io::ErrorKind::Other, "Game Over!")) */
-    Ok(())
+    #[test]
+    fn giving_up_is_a_loss_whatever_the_score() {
+        let mut game = game();
+        game.score = 2;
+        press(&mut game, KeyCode::Esc);
+
+        assert!(game.progress.over);
+        assert_eq!(
+            game.result(),
+            GameResult {
+                won: false,
+                score: 2,
+                misses: MAX_MISSES,
+                duration: Duration::ZERO
+            }
+        );
+    }
+
+    #[test]
+    fn frames_show_the_field() {
+        let mut game = game();
+        drop_item(&mut game, 1);
+        fall(&mut game, 1);
+
+        assert_eq!(
+            game.frame().to_string(),
+            [
+                "Caught 0/2  Missed 0/3  60s left  (←/→ to move, q to give up)",
+                "+-----+",
+                "|     |",
+                "| *   |",
+                "|     |",
+                "|  @  |",
+                "+-----+",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn a_headless_game_left_alone_is_lost() {
+        let (result, frames) = play_catching([]);
+
+        assert!(!result.won);
+        assert_eq!(result.misses, MAX_MISSES);
+        assert!(frames
+            .last()
+            .unwrap()
+            .ends_with("Game Over! Missed too many items."));
+    }
+
+    // A terminal that's always a little late getting back, the way a real one is
+    struct Laggy<'a>(headless::ScriptedInput, &'a headless::ManualClock);
+
+    impl Input for Laggy<'_> {
+        fn poll_key(&mut self, timeout: Duration) -> io::Result<Option<KeyEvent>> {
+            let key = self.0.poll_key(timeout)?;
+            self.1
+                .advance_to(self.1.now() + Duration::from_micros(1700));
+            Ok(key)
+        }
+    }
+
+    #[test]
+    fn a_game_on_tape_plays_again_exactly() {
+        let conn = fixtures::pets();
+        let operation = PendingOperation::new(StatementKind::Insert, "pets");
+        let ctx = GameContext {
+            conn: &conn,
+            seed: 7,
+            difficulty: Difficulty::Normal,
+            operation: &operation,
+        };
+
+        for game in [&Catching as &dyn MiniGame, &Snake] {
+            let clock = headless::ManualClock::default();
+            let keys = [
+                (1500, KeyCode::Left),
+                (2500, KeyCode::Up),
+                (2600, KeyCode::Right),
+            ];
+            let mut input = Laggy(headless::ScriptedInput::new(&clock, keys), &clock);
+            let mut renderer = headless::BufferRenderer::default();
+            let mut io = GameIo {
+                input: &mut input,
+                clock: &clock,
+                renderer: &mut renderer,
+            };
+            let (result, mut tape) = play_on_tape(game, &mut io, &ctx).unwrap();
+
+            assert_eq!(tape.size, (80, 24));
+            assert_eq!(headless::replay(game, &ctx, &tape).unwrap(), result);
+            // Cut short, the tape can't see the game through
+            tape.polls.truncate(tape.polls.len() / 2);
+            assert!(headless::replay(game, &ctx, &tape).is_err());
+        }
+    }
+
+    #[test]
+    fn a_headless_game_plays_the_same_from_the_same_seed() {
+        let keys = [(1500, KeyCode::Left), (2500, KeyCode::Right)];
+
+        assert_eq!(play_catching(keys), play_catching(keys));
+    }
+
+    #[test]
+    fn a_headless_game_can_be_given_up() {
+        let (result, frames) = play_catching([(500, KeyCode::Esc)]);
+
+        assert_eq!(
+            result,
+            GameResult {
+                duration: Duration::from_millis(500),
+                ..GameResult::forfeit()
+            }
+        );
+        assert!(frames.last().unwrap().ends_with("That counts as a loss."));
+    }
+
+    #[test]
+    fn the_snake_is_as_long_as_the_table() {
+        let game = snake(4);
+
+        assert_eq!(game.body.len(), 4);
+        assert_eq!(game.body[0], Point { x: 3, y: 4 });
+        assert_eq!(game.direction, Direction::Right);
+        assert!(game.news.starts_with("Your snake is 4 row(s) long."));
+    }
+
+    #[test]
+    fn big_tables_are_squeezed_onto_the_field() {
+        let game = snake(1000);
+
+        // A third of the 5x5 field
+        assert_eq!(game.body.len(), 8);
+        assert!(game.news.contains("125 to a segment"));
+        // Coiled along the bottom and back, heading up into the open
+        assert_eq!(game.body[0], Point { x: 2, y: 3 });
+        assert_eq!(game.direction, Direction::Left);
+    }
+
+    #[test]
+    fn the_snake_eats_what_was_being_inserted() {
+        let mut game = snake(3);
+        game.food = Some((Point { x: 3, y: 4 }, "rex".to_string()));
+        game.update(Duration::ZERO);
+
+        assert_eq!(game.eaten, 1);
+        assert_eq!(game.body.len(), 4);
+        assert_eq!(game.news, "Yum, rex!");
+        assert_eq!(game.food.as_ref().unwrap().1, "rex");
+    }
+
+    #[test]
+    fn crashing_costs_a_segment_and_turns_the_snake() {
+        let mut game = snake(3);
+        game.food = None;
+        // Two steps to the wall, then into it
+        for _ in 0..3 {
+            game.update(Duration::ZERO);
+        }
+
+        assert_eq!(game.crashes, 1);
+        assert_eq!(game.body.len(), 2);
+        assert_eq!(game.direction, Direction::Up);
+        assert!(!game.progress.over);
+    }
+
+    #[test]
+    fn the_snake_cant_turn_back_on_itself() {
+        let mut game = snake(3);
+        game.food = None;
+        game.handle_key(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+        game.update(Duration::ZERO);
+
+        assert_eq!(game.direction, Direction::Right);
+        assert_eq!(game.body[0], Point { x: 3, y: 4 });
+    }
+
+    #[test]
+    fn a_snake_with_nothing_left_is_game_over() {
+        let mut game = snake(1);
+        game.food = None;
+        game.body = VecDeque::from([Point { x: 4, y: 4 }]);
+        game.update(Duration::ZERO);
+
+        assert!(game.progress.over);
+        assert!(!game.won());
+        assert!(game
+            .frame()
+            .to_string()
+            .ends_with("Game Over! There's no snake left."));
+    }
+
+    #[test]
+    fn every_crash_costs_a_row_even_in_a_win() {
+        let won = GameResult {
+            won: true,
+            score: SNAKE_TARGET,
+            misses: 2,
+            duration: Duration::ZERO,
+        };
+
+        assert_eq!(Snake.rows_owed(&won, Difficulty::Nightmare), Some(2));
+        assert_eq!(Catching.rows_owed(&won, Difficulty::Nightmare), None);
+    }
+
+    #[test]
+    fn a_headless_snake_left_alone_crashes() {
+        let conn = fixtures::pets();
+        let mut operation = PendingOperation::new(StatementKind::Insert, "pets");
+        operation.values = vec!["4".to_string(), "bo".to_string()];
+
+        let (result, frames) = play_game(&Snake, &conn, &operation, []);
+        assert!(frames[0].contains("Your snake is 3 row(s) long."));
+        assert!(!result.won);
+        assert!(result.misses >= 1);
+        assert_eq!(
+            play_game(&Snake, &conn, &operation, []),
+            (result, frames),
+            "the same seed plays the same game"
+        );
+    }
+
+    #[test]
+    fn a_table_without_rows_is_a_free_pass_at_snake() {
+        let conn = fixtures::pets();
+        conn.execute("DELETE FROM pets", []).unwrap();
+        let mut operation = PendingOperation::new(StatementKind::Insert, "pets");
+        operation.values = vec!["4".to_string(), "bo".to_string()];
+
+        let (result, frames) = play_game(&Snake, &conn, &operation, []);
+        assert!(result.won);
+        assert_eq!(result.misses, 0);
+        assert!(frames[0].contains("There's no table here to make a snake of."));
+        assert!(frames
+            .last()
+            .unwrap()
+            .ends_with("Free pass! No rows, no snake."));
+
+        // Nor is a table that isn't there yet
+        let operation = PendingOperation::new(StatementKind::Insert, "vets");
+        assert!(play_game(&Snake, &conn, &operation, []).0.won);
+    }
 }
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rusqlite::Connection;
+
+use crate::background::{self, Background};
+use crate::rows::{self, RowData};
+use crate::session::say;
+use crate::{bitrot, fire};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+// Lets a gremlin loose: a little creature living on its own thread, with its own connection
+// to the database file, messing with user tables on a timer until it's dropped
+pub fn start(
+    path: &str,
+    seed: u64,
+    interval: Duration,
+) -> Result<Background, Box<dyn std::error::Error>> {
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(background::BUSY_TIMEOUT)?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    Ok(Background::start(interval, move || {
+        match mischief(&conn, &mut rng) {
+            Ok(Some((table_name, outcome))) => {
+                let message = format!("👹 The gremlin {} in '{}'!", outcome, table_name);
+                background::report(&conn, "gremlin", &table_name, &outcome, &message);
+            }
+            Ok(None) => {}
+            Err(e) => say!("\n👹 The gremlin tripped over something: {}", e),
+        }
+    }))
+}
+
+// One random insert, update or delete. Returns the table and what was done to it. Like any
+// other write it keeps the checksums up to date, and burning tables are off limits.
+fn mischief(
+    conn: &Connection,
+    rng: &mut StdRng,
+) -> Result<Option<(String, String)>, Box<dyn std::error::Error>> {
+    let mut tables = Vec::new();
+    for table_name in rows::user_tables(conn)? {
+        if fire::guard_write(conn, &table_name).is_ok() {
+            tables.push(table_name);
+        }
+    }
+    let Some(table_name) = tables.choose(rng) else {
+        return Ok(None);
+    };
+    // Gremlins can't make something out of nothing, every value is borrowed from a real row
+    let rows = rows::fetch_rows(conn, table_name, "1 = 1")?;
+    let Some((rowid, data)) = rows.choose(rng) else {
+        return Ok(None);
+    };
+
+    let outcome = match rng.gen_range(0..3) {
+        0 => {
+            let copied: RowData = data
+                .keys()
+                .map(|column| {
+                    let (_, donor) = rows.choose(rng).expect("rows aren't empty");
+                    (column.clone(), donor[column].clone())
+                })
+                .collect();
+            // A primary key or UNIQUE column can turn this down, which the gremlin shrugs off
+            if rows::insert_row(conn, table_name, &copied).is_err() {
+                return Ok(None);
+            }
+            bitrot::checksum_row(conn, table_name, conn.last_insert_rowid())?;
+            "snuck in a row".to_string()
+        }
+        1 => {
+            let columns: Vec<&String> = data.keys().collect();
+            let column = columns.choose(rng).expect("rows have columns");
+            let (_, donor) = rows.choose(rng).expect("rows aren't empty");
+            conn.execute(
+                &format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", table_name, column),
+                rusqlite::params![rows::from_json(&donor[column.as_str()]), rowid],
+            )?;
+            bitrot::checksum_row(conn, table_name, *rowid)?;
+            format!("scribbled over {}", column)
+        }
+        _ => {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE rowid = ?1", table_name),
+                [rowid],
+            )?;
+            bitrot::forget_rows(conn, table_name, &[*rowid])?;
+            "chewed up a row".to_string()
+        }
+    };
+
+    Ok(Some((table_name.clone(), outcome)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rows::fixtures;
+
+    #[test]
+    fn mischief_is_not_mistaken_for_bit_rot() {
+        let conn = fixtures::pets();
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..50 {
+            mischief(&conn, &mut rng).unwrap();
+        }
+
+        assert!(bitrot::scrub(&conn, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn burning_tables_are_left_alone() {
+        let conn = fixtures::pets();
+        fire::ignite(&conn, "pets").unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        assert!(mischief(&conn, &mut rng).unwrap().is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+
+use rusqlite::{params, Connection};
+
+pub const JOURNAL_TABLE: &str = "_wacky_journal";
+
+// One thing the chaos did, written down for the inevitable post-mortem
+pub struct JournalEntry {
+    pub id: i64,
+    pub happened_at: String,
+    pub statement: String,
+    pub effect: String,
+    pub table_name: String,
+    pub rows_affected: usize,
+    pub outcome: String,
+}
+
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>4}  {}  {:<18} {:<16} rows: {:<4} {}\n      {}",
+            self.id,
+            self.happened_at,
+            self.effect,
+            self.table_name,
+            self.rows_affected,
+            self.outcome,
+            self.statement
+        )
+    }
+}
+
+pub fn ensure_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY,
+                happened_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                statement TEXT NOT NULL,
+                effect TEXT NOT NULL,
+                table_name TEXT NOT NULL,
+                rows_affected INTEGER NOT NULL,
+                outcome TEXT NOT NULL
+            )",
+            JOURNAL_TABLE
+        ),
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn record(
+    conn: &Connection,
+    statement: &str,
+    effect: &str,
+    table_name: &str,
+    rows_affected: usize,
+    outcome: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (statement, effect, table_name, rows_affected, outcome)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            JOURNAL_TABLE
+        ),
+        params![statement, effect, table_name, rows_affected, outcome],
+    )?;
+
+    Ok(())
+}
+
+// Entries oldest first. A filter keeps only the entries for that table or effect.
+pub fn entries(
+    conn: &Connection,
+    filter: Option<&str>,
+) -> Result<Vec<JournalEntry>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, happened_at, statement, effect, table_name, rows_affected, outcome
+         FROM {}
+         WHERE ?1 IS NULL OR table_name = ?1 OR effect = ?1
+         ORDER BY id",
+        JOURNAL_TABLE
+    ))?;
+    let entries = stmt
+        .query_map([filter], |row| {
+            Ok(JournalEntry {
+                id: row.get(0)?,
+                happened_at: row.get(1)?,
+                statement: row.get(2)?,
+                effect: row.get(3)?,
+                table_name: row.get(4)?,
+                rows_affected: row.get(5)?,
+                outcome: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<JournalEntry>, _>>()?;
+
+    Ok(entries)
+}
+
+// How often an effect struck and how many rows it got its hands on
+pub struct EffectSummary {
+    pub effect: String,
+    pub times: usize,
+    pub rows_affected: usize,
+}
+
+pub fn summary(conn: &Connection) -> Result<Vec<EffectSummary>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT effect, COUNT(*), SUM(rows_affected) FROM {} GROUP BY effect ORDER BY COUNT(*) DESC, effect",
+        JOURNAL_TABLE
+    ))?;
+    let summary = stmt
+        .query_map([], |row| {
+            Ok(EffectSummary {
+                effect: row.get(0)?,
+                times: row.get(1)?,
+                rows_affected: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<EffectSummary>, _>>()?;
+
+    Ok(summary)
+}
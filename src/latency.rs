@@ -0,0 +1,159 @@
+use std::fmt;
+use std::io::{stdout, IsTerminal, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::{
+    cursor, execute,
+    terminal::{Clear, ClearType},
+};
+use rand::{rngs::StdRng, Rng};
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_FRAME_TIME: Duration = Duration::from_millis(80);
+
+// How steep the long tail is. Lower means the odd statement takes a lot longer.
+const LONG_TAIL_SHAPE: f64 = 1.5;
+// Even the longest tail has to end somewhere
+const LONG_TAIL_CAP: f64 = 20.0;
+// No statement waits longer than this, whatever the config says
+pub const MAX_DELAY: Duration = Duration::from_secs(600);
+// The slowest a single row can be made
+pub const MAX_PER_ROW_MS: f64 = 10_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LatencyDistribution {
+    Off,
+    Fixed { ms: u64 },
+    Uniform { min_ms: u64, max_ms: u64 },
+    // Mostly close to the median, but every now and then painfully slow
+    LongTail { median_ms: u64 },
+}
+
+pub struct LatencyConfig {
+    pub distribution: LatencyDistribution,
+    // Extra delay for every row in the table being worked on
+    pub per_row_ms: f64,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self {
+            distribution: LatencyDistribution::Off,
+            per_row_ms: 0.0,
+        }
+    }
+}
+
+impl LatencyConfig {
+    pub fn is_off(&self) -> bool {
+        self.distribution == LatencyDistribution::Off && self.per_row_ms <= 0.0
+    }
+
+    pub fn sample(&self, rng: &mut StdRng, row_count: usize) -> Duration {
+        let base_ms = match self.distribution {
+            LatencyDistribution::Off => 0.0,
+            LatencyDistribution::Fixed { ms } => ms as f64,
+            LatencyDistribution::Uniform { min_ms, max_ms } => {
+                rng.gen_range(min_ms..=max_ms.max(min_ms)) as f64
+            }
+            LatencyDistribution::LongTail { median_ms } => {
+                // Pareto distribution, scaled so half of all statements are faster than the median
+                let scale = median_ms as f64 / 2f64.powf(1.0 / LONG_TAIL_SHAPE);
+                let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+                (scale / u.powf(1.0 / LONG_TAIL_SHAPE)).min(median_ms as f64 * LONG_TAIL_CAP)
+            }
+        };
+
+        let total_ms = base_ms + self.per_row_ms * row_count as f64;
+        Duration::try_from_secs_f64(total_ms / 1000.0)
+            .unwrap_or(MAX_DELAY)
+            .min(MAX_DELAY)
+    }
+}
+
+impl fmt::Display for LatencyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.distribution {
+            LatencyDistribution::Off => write!(f, "off")?,
+            LatencyDistribution::Fixed { ms } => write!(f, "fixed {}ms", ms)?,
+            LatencyDistribution::Uniform { min_ms, max_ms } => {
+                write!(f, "uniform {}-{}ms", min_ms, max_ms)?
+            }
+            LatencyDistribution::LongTail { median_ms } => {
+                write!(f, "long tail, median {}ms", median_ms)?
+            }
+        }
+
+        if self.per_row_ms > 0.0 {
+            write!(f, ", plus {}ms per row", self.per_row_ms)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Waits out the delay, showing a spinner so nobody thinks we crashed
+pub fn wait(delay: Duration) {
+    if delay.is_zero() {
+        return;
+    }
+
+    let mut stdout = stdout();
+    if !stdout.is_terminal() {
+        thread::sleep(delay);
+        return;
+    }
+
+    let start = Instant::now();
+    let mut frame = 0;
+    while start.elapsed() < delay {
+        let _ = execute!(
+            stdout,
+            cursor::MoveToColumn(0),
+            Clear(ClearType::CurrentLine)
+        );
+        print!(
+            "{} thinking...",
+            SPINNER_FRAMES[frame % SPINNER_FRAMES.len()]
+        );
+        let _ = stdout.flush();
+
+        thread::sleep(SPINNER_FRAME_TIME.min(delay.saturating_sub(start.elapsed())));
+        frame += 1;
+    }
+
+    let _ = execute!(
+        stdout,
+        cursor::MoveToColumn(0),
+        Clear(ClearType::CurrentLine)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn no_config_waits_forever() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let config = LatencyConfig {
+            distribution: LatencyDistribution::Fixed { ms: u64::MAX },
+            per_row_ms: f64::INFINITY,
+        };
+
+        assert_eq!(config.sample(&mut rng, 1_000_000), MAX_DELAY);
+    }
+
+    #[test]
+    fn per_row_latency_adds_up() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let config = LatencyConfig {
+            distribution: LatencyDistribution::Fixed { ms: 100 },
+            per_row_ms: 2.5,
+        };
+
+        assert_eq!(config.sample(&mut rng, 40), Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+use std::time::Duration;
+
+use rusqlite::{params, Connection};
+
+use crate::game::GameResult;
+
+pub const LEADERBOARD_TABLE: &str = "_wacky_leaderboard";
+// How many places LEADERBOARD shows for each game
+pub const PLACES_SHOWN: usize = 5;
+
+// Who to put on the leaderboard when nobody said
+pub const DEFAULT_PLAYER: &str = "anonymous";
+
+// One game played, win or lose
+pub struct Score {
+    pub played_at: String,
+    pub player: String,
+    pub game: String,
+    pub won: bool,
+    pub score: u32,
+    pub misses: u32,
+    pub duration: Duration,
+    // The statement the game was played for
+    pub statement: String,
+}
+
+// A score along with where it placed among its game's scores
+pub struct Placing {
+    pub place: usize,
+    pub score: Score,
+}
+
+impl fmt::Display for Placing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let score = &self.score;
+        write!(
+            f,
+            "{:>4}. {:<16} score: {:<4} misses: {:<3} {:>5.1}s  {:<4}  {}\n      {}",
+            self.place,
+            score.player,
+            score.score,
+            score.misses,
+            score.duration.as_secs_f64(),
+            if score.won { "won" } else { "lost" },
+            score.played_at,
+            score.statement
+        )
+    }
+}
+
+pub fn ensure_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY,
+                played_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                player TEXT NOT NULL,
+                game TEXT NOT NULL,
+                won INTEGER NOT NULL,
+                score INTEGER NOT NULL,
+                misses INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                statement TEXT NOT NULL
+            )",
+            LEADERBOARD_TABLE
+        ),
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn record(
+    conn: &Connection,
+    player: &str,
+    game: &str,
+    result: &GameResult,
+    statement: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (player, game, won, score, misses, duration_ms, statement)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            LEADERBOARD_TABLE
+        ),
+        params![
+            player,
+            game,
+            result.won,
+            result.score,
+            result.misses,
+            result.duration.as_millis() as i64,
+            statement
+        ],
+    )?;
+
+    Ok(())
+}
+
+// The best `places` scores of every game, or of just the one. The highest score wins, and
+// a tie goes to fewer misses, then to whoever was quicker about it.
+pub fn top(
+    conn: &Connection,
+    game: Option<&str>,
+    places: usize,
+) -> Result<Vec<Placing>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT played_at, player, game, won, score, misses, duration_ms, statement, place
+         FROM (
+             SELECT *, ROW_NUMBER() OVER (
+                 PARTITION BY game ORDER BY score DESC, misses, duration_ms, id
+             ) AS place
+             FROM {}
+             WHERE ?1 IS NULL OR game = ?1
+         )
+         WHERE place <= ?2
+         ORDER BY game, place",
+        LEADERBOARD_TABLE
+    ))?;
+    let placings = stmt
+        .query_map(params![game, places as i64], |row| {
+            Ok(Placing {
+                place: row.get(8)?,
+                score: score_from_row(row)?,
+            })
+        })?
+        .collect::<Result<Vec<Placing>, _>>()?;
+
+    Ok(placings)
+}
+
+fn score_from_row(row: &rusqlite::Row) -> rusqlite::Result<Score> {
+    Ok(Score {
+        played_at: row.get(0)?,
+        player: row.get(1)?,
+        game: row.get(2)?,
+        won: row.get(3)?,
+        score: row.get(4)?,
+        misses: row.get(5)?,
+        duration: Duration::from_millis(row.get(6)?),
+        statement: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaderboard() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        ensure_table(&conn).unwrap();
+        conn
+    }
+
+    fn played(conn: &Connection, player: &str, game: &str, score: u32, misses: u32, ms: u64) {
+        let result = GameResult {
+            won: score >= 10,
+            score,
+            misses,
+            duration: Duration::from_millis(ms),
+        };
+        record(conn, player, game, &result, "SELECT * FROM pets").unwrap();
+    }
+
+    fn standings(placings: &[Placing]) -> Vec<(&str, usize, &str)> {
+        placings
+            .iter()
+            .map(|p| (p.score.game.as_str(), p.place, p.score.player.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn the_best_scores_of_every_game_come_first() {
+        let conn = leaderboard();
+        played(&conn, "ana", "catching", 4, 3, 9000);
+        played(&conn, "bo", "catching", 10, 1, 20000);
+        played(&conn, "cy", "trivia", 2, 1, 5000);
+        played(&conn, "ana", "trivia", 3, 0, 6000);
+
+        assert_eq!(
+            standings(&top(&conn, None, PLACES_SHOWN).unwrap()),
+            vec![
+                ("catching", 1, "bo"),
+                ("catching", 2, "ana"),
+                ("trivia", 1, "ana"),
+                ("trivia", 2, "cy")
+            ]
+        );
+    }
+
+    #[test]
+    fn ties_go_to_fewer_misses_then_the_quicker_player() {
+        let conn = leaderboard();
+        played(&conn, "slow", "catching", 10, 1, 25000);
+        played(&conn, "sloppy", "catching", 10, 2, 12000);
+        played(&conn, "quick", "catching", 10, 1, 15000);
+
+        assert_eq!(
+            standings(&top(&conn, Some("catching"), 2).unwrap()),
+            vec![("catching", 1, "quick"), ("catching", 2, "slow")]
+        );
+    }
+}
@@ -1,46 +1,94 @@
+mod background;
+mod bitrot;
+mod chaos;
+mod commands;
+mod decay;
+mod difficulty;
+mod drift;
+mod effects;
+mod fire;
 mod game;
+mod gremlin;
+mod journal;
+mod latency;
+mod leaderboard;
+mod pipeline;
+mod results;
+mod risk;
+mod rows;
+mod session;
+mod snapshot;
+mod trash;
+mod trivia;
 
 use std::io::{stdin, stdout, Write};
 
-use rand::Rng;
+use background::Background;
+use chaos::{
+    ChaosContext, ChaosProfile, ChaosSource, EffectOutcome, EffectRegistry, PendingOperation,
+    RollChaos, StatementKind, PROCEED_AS_NORMAL,
+};
+use decay::DecayPolicy;
+use difficulty::Difficulty;
+use game::GameRegistry;
+use latency::LatencyConfig;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use results::{Answer, ResultSet};
+use risk::Risk;
 use rusqlite::Connection;
-use serde::{Deserialize, Serialize};
+use session::{say, SessionHeader};
+use snapshot::{Changes, Snapshot, SnapshotStack};
 use sqlparser::{
     ast::{Assignment, ColumnOption, Ident, ObjectName, Statement},
     dialect::GenericDialect,
     parser::Parser,
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Record {
-    id: u32,
-    data: String,
-}
-
 struct WackyDB {
     conn: Connection,
+    // Where the database lives, for anything that needs a connection of its own
+    path: String,
+    // Every bit of chaos is drawn from this, so a session can be replayed from its seed
+    rng: StdRng,
+    seed: u64,
+    chaos_profile: ChaosProfile,
+    // What picks the effect for each statement, usually a roll of the chaos profile
+    chaos_source: Box<dyn ChaosSource>,
+    effects: EffectRegistry,
+    games: GameRegistry,
+    // Whose name goes on the leaderboard
+    player: String,
+    latency: LatencyConfig,
+    decay: DecayPolicy,
+    // The decay policy at work in the background, while there is one
+    decaying: Option<Background>,
+    pipeline: Vec<Box<dyn pipeline::Stage>>,
+    difficulty: Difficulty,
+    // Taken before destructive chaos, most recent last
+    snapshots: SnapshotStack,
+    // The last SELECT, for \honest
+    last_answer: Option<Answer>,
+    gremlin: Option<Background>,
 }
 
+#[derive(Clone)]
 pub struct ColumnDefinition {
     name: String,
     column_type: String,
     options: Vec<String>,
 }
 
-enum ChaosResult {
-    GamingTime,
-    DatabaseOnFire,
-    DataInTrash,
-    NothingHappened,
-    ProceedAsNormal,
-}
-
 impl WackyDB {
-    pub fn new(db_name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(
+        db_name: &str,
+        seed: u64,
+        chaos_source: Box<dyn ChaosSource>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let sanitized_name = sanitize_db_name(db_name)?;
 
         // Open a database connection
-        let conn = Connection::open(&sanitized_name)?;
+        let conn = Connection::open(sanitized_name)?;
+        conn.busy_timeout(background::BUSY_TIMEOUT)?;
         let _ = conn.execute(
             "CREATE TABLE IF NOT EXISTS records (
                     id INTEGER PRIMARY KEY
@@ -49,34 +97,232 @@ impl WackyDB {
             [],
         );
 
-        Ok(Self { conn })
+        ensure_internal_tables(&conn)?;
+
+        let mut effects = EffectRegistry::default();
+        effects::register_builtin_effects(&mut effects);
+        let mut games = GameRegistry::default();
+        game::register_builtin_games(&mut games);
+
+        Ok(Self {
+            conn,
+            path: sanitized_name.to_string(),
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            chaos_profile: ChaosProfile::default(),
+            chaos_source,
+            effects,
+            games,
+            player: leaderboard::DEFAULT_PLAYER.to_string(),
+            latency: LatencyConfig::default(),
+            decay: DecayPolicy::default(),
+            decaying: None,
+            pipeline: pipeline::default_stages(),
+            difficulty: Difficulty::default(),
+            snapshots: SnapshotStack::default(),
+            last_answer: None,
+            gremlin: None,
+        })
     }
 
-    fn chaos_engine() -> ChaosResult {
-        let mut rng = rand::thread_rng();
-        if rng.gen_range(0..10) < 3 {
-            println!("Something WaCky is hApennning!");
-            let roll_outcome = rng.gen_range(0..5);
-            match roll_outcome {
-                0 => {
-                    return ChaosResult::GamingTime;
-                }
-                1 => {
-                    return ChaosResult::DatabaseOnFire;
-                }
-                2 => {
-                    return ChaosResult::DataInTrash;
-                }
-                _ => {
-                    return ChaosResult::NothingHappened;
+    // Restart the chaos sequence as if the database had just been opened with this seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.seed = seed;
+    }
+
+    // The prompt shows the difficulty, and burning tables so nobody can miss the smoke
+    pub fn prompt(&self) -> String {
+        // Boring mode doesn't believe in fire, so there's nothing to show
+        if self.difficulty.is_boring() {
+            return format!("wacky_db [{}]> ", self.difficulty);
+        }
+
+        match fire::burning_tables(&self.conn) {
+            Ok(burning) if !burning.is_empty() => {
+                let names: Vec<String> = burning.into_iter().map(|(name, _)| name).collect();
+                format!("wacky_db [{}] 🔥[{}]> ", self.difficulty, names.join(", "))
+            }
+            _ => format!("wacky_db [{}]> ", self.difficulty),
+        }
+    }
+
+    // Time passes between statements, and fires don't wait for anyone
+    pub fn tick(&mut self, sql: &str) {
+        if self.difficulty.is_boring() {
+            return;
+        }
+
+        match fire::spread(&self.conn, &mut self.rng, self.difficulty) {
+            Ok(events) => {
+                for event in events {
+                    match event {
+                        fire::FireEvent::Spread { from, to } => {
+                            say!("🔥 The fire spread from '{}' to '{}'!", from, to);
+                            self.journal(sql, "fire", &to, 0, &format!("spread from '{}'", from));
+                        }
+                        fire::FireEvent::RowBurned { table_name } => {
+                            say!("🔥 A row of '{}' burned to a crisp.", table_name);
+                            self.journal(sql, "fire", &table_name, 1, "row burned");
+                        }
+                    }
                 }
             }
+            Err(e) => say!("The fire got out of hand: {}", e),
         }
+    }
 
-        ChaosResult::ProceedAsNormal
+    // Starts the decay over with the current policy and difficulty, or stops it if there's
+    // nothing left to decay
+    pub fn restart_decay(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.decaying = None;
+        if self.decay.is_off() || self.difficulty.is_boring() {
+            return Ok(());
+        }
+        if !background::can_reach(&self.path) {
+            return Err("Decay can't get at a database that only lives in memory.".into());
+        }
+        if background::sits_out_sessions() {
+            return Err("Decay sits out recorded and replayed sessions.".into());
+        }
+
+        let decaying = decay::start(
+            &self.path,
+            self.rng.gen(),
+            self.decay.clone(),
+            self.difficulty,
+        )?;
+        self.decaying = Some(decaying);
+        Ok(())
+    }
+
+    // Writes a chaos event to the journal. A journal that can't be written isn't worth
+    // stopping the chaos for.
+    fn journal(&self, sql: &str, effect: &str, table_name: &str, rows: usize, outcome: &str) {
+        if let Err(e) = journal::record(&self.conn, sql, effect, table_name, rows, outcome) {
+            say!("The journal got chaos'd too: {}", e);
+        }
+    }
+
+    // Make everything slow because... well, because
+    fn slow_down(&mut self, table_name: &str) {
+        if self.latency.is_off() || self.difficulty.is_boring() {
+            return;
+        }
+
+        let row_count = self.row_count(table_name).unwrap_or(0);
+        latency::wait(self.latency.sample(&mut self.rng, row_count));
     }
 
+    fn row_count(&self, table_name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        if !self.table_exists(table_name)? {
+            return Ok(0);
+        }
+
+        let count: i64 =
+            self.conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table_name), [], |row| {
+                    row.get(0)
+                })?;
+        Ok(count as usize)
+    }
+
+    // Rolls for an effect and lets it loose on the operation. The riskier the operation, the
+    // likelier the roll comes up chaos. Returns an error if the chaos stopped the operation.
+    fn chaos_engine(
+        &mut self,
+        operation: &mut PendingOperation,
+        risk: &Risk,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.difficulty.is_boring() {
+            return Ok(());
+        }
+
+        let frequency = self.chaos_frequency(risk);
+        let rolled = self.chaos_source.pick(
+            &self.chaos_profile,
+            operation.kind,
+            &mut self.rng,
+            frequency,
+        );
+        session::note_chaos(rolled.as_deref().unwrap_or(PROCEED_AS_NORMAL));
+        let effect = rolled.and_then(|name| self.effects.get(&name));
+        let Some(effect) = effect else {
+            return Ok(());
+        };
+
+        say!("Something WaCky is hApennning!");
+        let mut ctx = ChaosContext {
+            conn: &self.conn,
+            rng: &mut self.rng,
+            difficulty: self.difficulty,
+            games: &self.games,
+            player: &self.player,
+            rows_affected: 0,
+            changes: Changes::default(),
+        };
+        let outcome = effect.apply(&mut ctx, operation);
+        let rows_affected = ctx.rows_affected;
+        let changes = ctx.changes;
+        if !changes.is_empty() {
+            self.snapshots.push(Snapshot::new(
+                effect.name(),
+                &operation.sql,
+                &operation.table_name,
+                changes,
+            ));
+        }
+        let journal_outcome = match &outcome {
+            Ok(EffectOutcome::Proceed) => "proceeded".to_string(),
+            Ok(EffectOutcome::Abort(message)) => format!("aborted: {}", message),
+            Err(e) => format!("failed: {}", e),
+        };
+        self.journal(
+            &operation.sql,
+            effect.name(),
+            &operation.table_name,
+            rows_affected,
+            &journal_outcome,
+        );
+
+        match outcome? {
+            EffectOutcome::Proceed => Ok(()),
+            EffectOutcome::Abort(message) => Err(message.into()),
+        }
+    }
+
+    // How often chaos strikes an operation this risky: the difficulty's frequency, scaled by
+    // the risk score
+    fn chaos_frequency(&self, risk: &Risk) -> u32 {
+        self.difficulty.frequency() * risk.score / 100
+    }
+
+    // Takes back what the most recent destructive chaos did to the database, leaving
+    // everything since alone. Returns the snapshot that was undone, or None if there's nothing
+    // left to undo.
+    pub fn undo_chaos(&mut self) -> Result<Option<Snapshot>, Box<dyn std::error::Error>> {
+        let Some(snapshot) = self.snapshots.pop() else {
+            return Ok(None);
+        };
+
+        snapshot::restore(&self.conn, &snapshot)?;
+        self.journal(
+            &snapshot.statement,
+            &snapshot.effect,
+            &snapshot.table_name,
+            0,
+            "undone",
+        );
+
+        Ok(Some(snapshot))
+    }
+
+    // WackyDB's internal tables never exist as far as the user is concerned
     fn table_exists(&self, table_name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        if is_internal_table(table_name) {
+            return Ok(false);
+        }
+
         let query = "SELECT name FROM sqlite_master WHERE type='table' AND name=?";
 
         let mut stmt = self.conn.prepare(query)?;
@@ -86,257 +332,227 @@ impl WackyDB {
         Ok(rows.next()?.is_some())
     }
 
-    pub fn create_table(
-        &self,
-        table_name: &str,
-        columns: &[ColumnDefinition], // Use the new structure
+    // Sends a statement through every stage of the pipeline and, if it survives, runs it
+    pub fn execute(
+        &mut self,
+        mut operation: PendingOperation,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        if self.table_exists(table_name)? {
-            return Err(format!("Table '{}' already exists.", table_name).into());
-        }
+        // The stages need the whole database, so they sit out while they run
+        let stages = std::mem::take(&mut self.pipeline);
+        let result = pipeline::run(&stages, self, &mut operation);
+        self.pipeline = stages;
 
-        let chaos_result = Self::chaos_engine();
+        result
+    }
 
-        match chaos_result {
-            ChaosResult::GamingTime => {
-                println!("It's time to play a classic!");
-                let outcome = game::play_game();
-                match outcome {
-                    Ok(_) => {
-                        return Ok(());
-                    }
-                    Err(_) => {
-                        return Err(
-                            "You lost the game! You also lost some PRECIOUS data as well!".into(),
-                        );
-                    }
+    fn run_operation(
+        &mut self,
+        operation: &PendingOperation,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match operation.kind {
+            StatementKind::Create => self.create_table(operation),
+            StatementKind::Insert => self.insert(operation),
+            StatementKind::Select => self.select(operation),
+            StatementKind::Update => self.update(operation),
+            StatementKind::Drop => self.drop_table(operation),
+        }
+    }
+
+    fn create_table(&self, operation: &PendingOperation) -> Result<(), Box<dyn std::error::Error>> {
+        // Construct the SQL CREATE TABLE statement
+        let columns_definition: Vec<String> = operation
+            .columns
+            .iter()
+            .map(|col| {
+                let mut definition = format!("{} {}", col.name, col.column_type);
+                for option in &col.options {
+                    definition.push_str(&format!(" {}", option));
                 }
-            }
-            ChaosResult::DatabaseOnFire => {
-                return Err("Oh no, the database is on fire! 🔥".into());
-            }
-            ChaosResult::DataInTrash => {
-                return Err(
-                    "Oops, I dropped your data in the trash! I think I can recover it?".into(),
-                );
-            }
-            ChaosResult::NothingHappened => {
-                return Err("Nah, nothing happened. I'm feelin a little QUIRKY today".into());
-            }
-            _ => {
-                // Construct the SQL CREATE TABLE statement
-                let columns_definition: Vec<String> = columns
-                    .iter()
-                    .map(|col| {
-                        let mut definition = format!("{} {}", col.name, col.column_type);
-                        for option in &col.options {
-                            definition.push_str(&format!(" {}", option));
-                        }
-                        definition
-                    })
-                    .collect();
-                let columns_str = columns_definition.join(", ");
+                definition
+            })
+            .collect();
+        let columns_str = columns_definition.join(", ");
 
-                let sql = format!("CREATE TABLE {} ({})", table_name, columns_str);
+        let sql = format!("CREATE TABLE {} ({})", operation.table_name, columns_str);
 
-                // Prepare and execute the statement
-                let mut stmt = self.conn.prepare(&sql)?;
-                stmt.execute([])?; // No parameters to bind since the SQL is already constructed.
-            }
-        }
+        // Prepare and execute the statement
+        let mut stmt = self.conn.prepare(&sql)?;
+        stmt.execute([])?; // No parameters to bind since the SQL is already constructed.
 
         Ok(())
     }
 
-    pub fn insert(
-        &self,
-        table_name: &str,
-        values: Vec<String>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let chaos_result = Self::chaos_engine();
-
-        match chaos_result {
-            ChaosResult::GamingTime => {
-                println!("It's time to play a classic!");
-                let outcome = game::play_game();
-                match outcome {
-                    Ok(_) => {
-                        return Ok(());
-                    }
-                    Err(_) => {
-                        return Err(
-                            "You lost the game! You also lost some PRECIOUS data as well!".into(),
-                        );
-                    }
-                }
-            }
-            ChaosResult::DatabaseOnFire => {
-                return Err("Oh no, the database is on fire! 🔥".into());
-            }
-            ChaosResult::DataInTrash => {
-                return Err(
-                    "Oops, I dropped your data in the trash! I think I can recover it?".into(),
-                );
-            }
-            ChaosResult::NothingHappened => {
-                return Err("Nah, nothing happened. I'm feelin a little QUIRKY today".into());
-            }
-            _ => {
-                // Prepare the SQL statement
-                let placeholders = values.iter().map(|_| "?").collect::<Vec<&str>>().join(", ");
-                let sql = format!("INSERT INTO {} VALUES ({})", table_name, placeholders);
-                let mut stmt = self.conn.prepare(&sql)?;
-
-                // Execute the statement with the values
-                let params: Vec<&dyn rusqlite::ToSql> =
-                    values.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
-                stmt.execute(params.as_slice())?;
-            }
-        }
+    fn insert(&self, operation: &PendingOperation) -> Result<(), Box<dyn std::error::Error>> {
+        // Prepare the SQL statement
+        let placeholders = operation
+            .values
+            .iter()
+            .map(|_| "?")
+            .collect::<Vec<&str>>()
+            .join(", ");
+        let sql = format!(
+            "INSERT INTO {} VALUES ({})",
+            operation.table_name, placeholders
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+
+        // Execute the statement with the values
+        let params: Vec<&dyn rusqlite::ToSql> = operation
+            .values
+            .iter()
+            .map(|v| v as &dyn rusqlite::ToSql)
+            .collect();
+        stmt.execute(params.as_slice())?;
+        bitrot::checksum_row(
+            &self.conn,
+            &operation.table_name,
+            self.conn.last_insert_rowid(),
+        )?;
 
         Ok(())
     }
 
-    fn select(
-        &self,
-        table_name: &str,
-        columns: &str,
-        where_clauses: &Vec<Assignment>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let chaos_result = Self::chaos_engine();
-
-        match chaos_result {
-            ChaosResult::GamingTime => {
-                println!("It's time to play a classic!");
-                let outcome = game::play_game();
-                match outcome {
-                    Ok(_) => {
-                        return Ok(());
-                    }
-                    Err(_) => {
-                        return Err(
-                            "You lost the game! You also lost some PRECIOUS data as well!".into(),
-                        );
-                    }
-                }
-            }
-            ChaosResult::DatabaseOnFire => {
-                return Err("Oh no, the database is on fire! 🔥".into());
-            }
-            ChaosResult::DataInTrash => {
-                return Err(
-                    "Oops, I dropped your data in the trash! I think I can recover it?".into(),
-                );
-            }
-            ChaosResult::NothingHappened => {
-                return Err("Nah, nothing happened. I'm feelin a little QUIRKY today".into());
-            }
-            _ => {
-                let mut where_str = String::new();
-                for assignment in where_clauses {
-                    match &assignment.value {
-                        sqlparser::ast::Expr::Value(value) => match value {
-                            sqlparser::ast::Value::SingleQuotedString(s) => {
-                                where_str.push_str(&format!("{} = '{}', ", assignment.target, s));
-                            }
-                            sqlparser::ast::Value::Number(n, _) => {
-                                where_str.push_str(&format!("{} = {}, ", assignment.target, n));
-                            }
-                            _ => {
-                                println!("Unimplemented value:\n {:?}", value);
-                            }
-                        },
-                        _ => {
-                            println!("Unimplemented expression:\n {:?}", assignment.value);
-                        }
-                    }
-                }
+    fn select(&mut self, operation: &PendingOperation) -> Result<(), Box<dyn std::error::Error>> {
+        let where_str = where_sql(&operation.where_clauses);
 
-                // Remove the trailing comma and space
-                where_str.pop();
-                where_str.pop();
-
-                if where_str.is_empty() {
-                    where_str = "1 = 1".to_string();
-                }
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}",
+            operation.projection, operation.table_name, where_str
+        );
+        let truth = ResultSet::query(&self.conn, &sql)?;
 
-                let sql = format!("SELECT {} FROM {} WHERE {}", columns, table_name, where_str);
-                let mut stmt = self.conn.prepare(&sql).unwrap();
-                let mut rows = stmt.query([]).unwrap();
+        let mut shown = truth.clone();
+        if let Some(effect) = operation
+            .lie
+            .as_deref()
+            .and_then(|name| self.effects.get(name))
+        {
+            let mut ctx = ChaosContext {
+                conn: &self.conn,
+                rng: &mut self.rng,
+                difficulty: self.difficulty,
+                games: &self.games,
+                player: &self.player,
+                rows_affected: 0,
+                changes: Changes::default(),
+            };
+            shown.lie = effect.distort(&mut ctx, &mut shown);
+        }
 
-                while let Some(row) = rows.next().unwrap() {
-                    println!("{:?}", row);
-                }
-            }
+        if !shown.rows.is_empty() {
+            say!("{}", shown);
         }
+        self.last_answer = Some(Answer {
+            statement: operation.sql.clone(),
+            truth,
+            shown,
+        });
 
         Ok(())
     }
 
-    fn update(
-        &self,
-        table_name: &str,
-        assignments: &Vec<Assignment>,
-        where_clauses: &Vec<Assignment>,
-    ) {
-        let mut set_str = String::new();
-        for assignment in assignments {
-            match &assignment.value {
-                sqlparser::ast::Expr::Value(value) => match value {
-                    sqlparser::ast::Value::SingleQuotedString(s) => {
-                        set_str.push_str(&format!("{} = '{}', ", assignment.target, s));
-                    }
-                    sqlparser::ast::Value::Number(n, _) => {
-                        set_str.push_str(&format!("{} = {}, ", assignment.target, n));
-                    }
-                    _ => {
-                        println!("Unimplemented value:\n {:?}", value);
-                    }
-                },
-                _ => {
-                    println!("Unimplemented expression:\n {:?}", assignment.value);
-                }
-            }
+    fn update(&self, operation: &PendingOperation) -> Result<(), Box<dyn std::error::Error>> {
+        let set_str = assignments_sql(&operation.assignments);
+        let where_str = where_sql(&operation.where_clauses);
+
+        let sql = format!(
+            "UPDATE {} SET {} WHERE {}",
+            operation.table_name, set_str, where_str
+        );
+        // The WHERE clause might not match anymore once the update is through
+        let updated: Vec<i64> = rows::fetch_rows(&self.conn, &operation.table_name, &where_str)?
+            .into_iter()
+            .map(|(rowid, _)| rowid)
+            .collect();
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        stmt.execute([])?;
+        for rowid in updated {
+            bitrot::checksum_row(&self.conn, &operation.table_name, rowid)?;
         }
 
-        // Remove the trailing comma and space
-        set_str.pop();
-        set_str.pop();
+        Ok(())
+    }
 
-        let mut where_str = String::new();
-        for assignment in where_clauses {
-            match &assignment.value {
-                sqlparser::ast::Expr::Value(value) => match value {
-                    sqlparser::ast::Value::SingleQuotedString(s) => {
-                        where_str.push_str(&format!("{} = '{}', ", assignment.target, s));
-                    }
-                    sqlparser::ast::Value::Number(n, _) => {
-                        where_str.push_str(&format!("{} = {}, ", assignment.target, n));
-                    }
-                    _ => {
-                        println!("Unimplemented value:\n {:?}", value);
-                    }
-                },
+    fn drop_table(&self, operation: &PendingOperation) -> Result<(), Box<dyn std::error::Error>> {
+        self.conn
+            .execute(&format!("DROP TABLE {}", operation.table_name), [])?;
+        bitrot::forget_table(&self.conn, &operation.table_name)?;
+
+        Ok(())
+    }
+}
+
+// Turns parsed assignments into "column = value" pairs, separated by commas
+fn assignments_sql(assignments: &[Assignment]) -> String {
+    let mut assignments_str = String::new();
+    for assignment in assignments {
+        match &assignment.value {
+            sqlparser::ast::Expr::Value(value) => match value {
+                sqlparser::ast::Value::SingleQuotedString(s) => {
+                    assignments_str.push_str(&format!("{} = '{}', ", assignment.target, s));
+                }
+                sqlparser::ast::Value::Number(n, _) => {
+                    assignments_str.push_str(&format!("{} = {}, ", assignment.target, n));
+                }
                 _ => {
-                    println!("Unimplemented expression:\n {:?}", assignment.value);
+                    say!("Unimplemented value:\n {:?}", value);
                 }
+            },
+            _ => {
+                say!("Unimplemented expression:\n {:?}", assignment.value);
             }
         }
+    }
 
-        // Remove the trailing comma and space
-        where_str.pop();
-        where_str.pop();
+    // Remove the trailing comma and space
+    assignments_str.pop();
+    assignments_str.pop();
+
+    assignments_str
+}
 
-        let sql = format!("UPDATE {} SET {} WHERE {}", table_name, set_str, where_str);
-        let mut stmt = self.conn.prepare(&sql).unwrap();
-        stmt.execute([]).unwrap();
+// Turns parsed WHERE assignments into SQL, matching every row when there are none
+fn where_sql(where_clauses: &[Assignment]) -> String {
+    let where_str = assignments_sql(where_clauses);
+    if where_str.is_empty() {
+        return "1 = 1".to_string();
     }
+
+    where_str
+}
+
+// Sets up every table WackyDB keeps for itself, if it isn't there yet
+pub(crate) fn ensure_internal_tables(conn: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+    trash::ensure_table(conn)?;
+    fire::ensure_table(conn)?;
+    decay::ensure_table(conn)?;
+    journal::ensure_table(conn)?;
+    bitrot::ensure_table(conn)?;
+    drift::ensure_table(conn)?;
+    leaderboard::ensure_table(conn)?;
+    Ok(())
 }
 
+// WackyDB keeps its own bookkeeping in tables with this prefix, out of the user's reach
+const INTERNAL_TABLE_PREFIX: &str = "_wacky_";
+
+fn is_internal_table(table_name: &str) -> bool {
+    table_name
+        .to_ascii_lowercase()
+        .starts_with(INTERNAL_TABLE_PREFIX)
+}
+
+const IN_MEMORY_DB_NAME: &str = ":memory:";
+
 fn sanitize_db_name(name: &str) -> Result<&str, &str> {
     if name.is_empty() {
         return Err("Database name cannot be empty, even for a wacky database!");
     }
+    // SQLite's name for a database that only lives as long as the connection
+    if name == IN_MEMORY_DB_NAME {
+        return Ok(name);
+    }
 
     let is_valid = name
         .chars()
@@ -350,364 +566,1050 @@ fn sanitize_db_name(name: &str) -> Result<&str, &str> {
     Ok(name)
 }
 
+// Replays run on a copy of the recorded database, leaving the real one alone
+const REPLAY_DB_NAME: &str = "wacky_db_replay.sqlite";
+
+#[derive(Default)]
+struct CliOptions {
+    seed: Option<u64>,
+    chaos_profile: Option<String>,
+    difficulty: Option<Difficulty>,
+    record: Option<String>,
+    replay: Option<String>,
+    player: Option<String>,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<CliOptions, String> {
+    let mut options = CliOptions::default();
+    let mut args = args.peekable();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+
+        match flag.as_str() {
+            "--seed" => {
+                let value = inline_value
+                    .or_else(|| args.next())
+                    .ok_or("--seed needs a number, even a wacky one")?;
+                let seed = value
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a seed I can grow chaos from", value))?;
+                options.seed = Some(seed);
+            }
+            "--chaos-profile" => {
+                let path = inline_value
+                    .or_else(|| args.next())
+                    .ok_or("--chaos-profile needs the path to a JSON chaos profile")?;
+                options.chaos_profile = Some(path);
+            }
+            "--difficulty" => {
+                let value = inline_value
+                    .or_else(|| args.next())
+                    .ok_or("--difficulty needs one of boring, easy, normal or nightmare")?;
+                options.difficulty = Some(value.parse()?);
+            }
+            "--record" => {
+                let path = inline_value
+                    .or_else(|| args.next())
+                    .ok_or("--record needs a file to write the session to")?;
+                options.record = Some(path);
+            }
+            "--replay" => {
+                let path = inline_value
+                    .or_else(|| args.next())
+                    .ok_or("--replay needs a session file recorded with --record")?;
+                options.replay = Some(path);
+            }
+            "--player" => {
+                let name = inline_value
+                    .or_else(|| args.next())
+                    .filter(|name| !name.trim().is_empty())
+                    .ok_or("--player needs a name for the leaderboard")?;
+                options.player = Some(name);
+            }
+            _ => return Err(format!("Unknown option '{}'", flag)),
+        }
+    }
+
+    if options.record.is_some() && options.replay.is_some() {
+        return Err("Recording a replay is a bit too meta, pick one".to_string());
+    }
+
+    Ok(options)
+}
+
 fn main() {
-    let db = WackyDB::new("wacky_db.sqlite").unwrap();
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            say!("{}", e);
+            say!(
+                "Usage: wacky_db [--seed <number>] [--chaos-profile <path>] [--difficulty <level>] [--player <name>] [--record <file> | --replay <file>]"
+            );
+            return;
+        }
+    };
+
+    // A replay starts over from everything the recording started with
+    let replaying = options.replay.is_some();
+    let (db_name, seed, chaos_profile, difficulty, player) = match &options.replay {
+        Some(path) => match session::replay(path, REPLAY_DB_NAME) {
+            Ok(header) => match header.difficulty.parse() {
+                Ok(difficulty) => (
+                    REPLAY_DB_NAME,
+                    header.seed,
+                    Some(header.chaos_profile),
+                    Some(difficulty),
+                    Some(header.player),
+                ),
+                // Any other difficulty would replay a different session
+                Err(e) => {
+                    say!("Couldn't replay session '{}': {}", path, e);
+                    return;
+                }
+            },
+            Err(e) => {
+                say!("Couldn't replay session '{}': {}", path, e);
+                return;
+            }
+        },
+        None => {
+            let chaos_profile = match &options.chaos_profile {
+                Some(path) => match ChaosProfile::load(path) {
+                    Ok(profile) => Some(profile),
+                    Err(e) => {
+                        say!("Couldn't load chaos profile '{}': {}", path, e);
+                        return;
+                    }
+                },
+                None => None,
+            };
+            let seed = options.seed.unwrap_or_else(rand::random);
+            // Whoever's logged in, unless they'd rather be someone else
+            let player = options
+                .player
+                .or_else(|| std::env::var("USER").ok())
+                .filter(|player| !player.trim().is_empty());
+            (
+                "wacky_db.sqlite",
+                seed,
+                chaos_profile,
+                options.difficulty,
+                player,
+            )
+        }
+    };
+
+    let mut db = WackyDB::new(db_name, seed, Box::new(RollChaos)).unwrap();
+    if let Some(profile) = chaos_profile {
+        if let Err(e) = profile.check(&db.effects) {
+            say!("Couldn't load chaos profile: {}", e);
+            return;
+        }
+        db.chaos_profile = profile;
+    }
+    if let Some(difficulty) = difficulty {
+        db.difficulty = difficulty;
+    }
+    if let Some(player) = player {
+        db.player = player;
+    }
+    if let Some(path) = &options.record {
+        let header = SessionHeader {
+            seed,
+            difficulty: db.difficulty.to_string(),
+            chaos_profile: db.chaos_profile.clone(),
+            player: db.player.clone(),
+            database: session::database_copy(path),
+        };
+        if let Err(e) = session::record(path, &db.conn, header) {
+            say!("Couldn't start recording to '{}': {}", path, e);
+            return;
+        }
+    }
     let sql_dialect = GenericDialect {};
 
-    println!("Welcome to WackyDB, the wackiest database you will see! (Today at least)");
+    say!("Welcome to WackyDB, the wackiest database you will see! (Today at least)");
+    say!(
+        "Chaos seed: {} (replay this session with --seed {})",
+        seed,
+        seed
+    );
     loop {
-        print!("wacky_db> ");
+        print!("{}", db.prompt());
         stdout().flush().unwrap();
 
         let mut input = String::new();
-        stdin().read_line(&mut input).unwrap();
+        if replaying {
+            input = session::next_replay_input().unwrap_or_default();
+            print!("{}", input);
+        } else {
+            stdin().read_line(&mut input).unwrap();
+        }
+        // Out of input, be it stdin or the recording
+        if input.is_empty() {
+            break;
+        }
 
-        if input.trim().eq_ignore_ascii_case("quit") {
-            println!("Goodbye! Thanks for using WackyDB!");
+        session::begin_step(&input);
+        let quit = input.trim().eq_ignore_ascii_case("quit");
+        if quit {
+            say!("Goodbye! Thanks for using WackyDB!");
+        } else if let Some(command) = commands::parse(&input) {
+            match command {
+                Ok(command) => commands::run(&mut db, command),
+                Err(e) => say!("{}", e),
+            }
+        } else {
+            run_sql(&mut db, &sql_dialect, &input);
+        }
+        if let Err(e) = session::end_step() {
+            say!("Couldn't write this one down for the session: {}", e);
+        }
+
+        if quit {
             break;
         }
+    }
 
-        match Parser::parse_sql(&sql_dialect, &input) {
-            Ok(statements) => {
-                for statement in statements {
-                    match statement {
-                        // Update to handle more options later
-                        Statement::CreateTable(create_table) => {
-                            let object_name = create_table.name.0;
-                            if object_name.len() > 1 {
-                                println!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
-                                continue;
-                            }
-                            let table_name = object_name[0].value.clone(); // Get the table name
-
-                            let columns_definition: Vec<ColumnDefinition> = create_table
-                                .columns
-                                .iter()
-                                .map(|column| {
-                                    let column_type = match &column.data_type {
-                                        sqlparser::ast::DataType::Integer(_) => {
-                                            "INTEGER".to_string()
-                                        }
-                                        sqlparser::ast::DataType::Text => "TEXT".to_string(),
-                                        _ => "UNKNOWN".to_string(), // Handle other types as necessary
-                                    };
+    if let Some((lines, diverged)) = session::finish() {
+        if diverged == 0 {
+            say!(
+                "Replayed {} line(s), every one of them just as wacky as before.",
+                lines
+            );
+        } else {
+            say!(
+                "Replayed {} line(s), {} of them went differently this time.",
+                lines,
+                diverged
+            );
+            std::process::exit(1);
+        }
+    }
+}
 
-                                    let options = column
-                                        .options
-                                        .iter()
-                                        .map(|opt| match opt.option {
-                                            ColumnOption::NotNull => "NOT NULL".to_string(),
-                                            ColumnOption::Unique {
-                                                is_primary,
-                                                characteristics, // Bahahaha I'll do this later
-                                            } => {
-                                                if is_primary {
-                                                    "PRIMARY KEY".to_string()
-                                                } else {
-                                                    "UNIQUE".to_string()
-                                                }
+fn run_sql(db: &mut WackyDB, sql_dialect: &GenericDialect, input: &str) {
+    for operation in plan_sql(sql_dialect, input) {
+        // The pipeline's logging stage reports how it went
+        let _ = db.execute(operation);
+    }
+}
+
+// Turns the input into the operations it would run, one per row for a multi-row INSERT and
+// one per table for DROP. Anything WackyDB can't run is reported and left out.
+fn plan_sql(sql_dialect: &GenericDialect, input: &str) -> Vec<PendingOperation> {
+    let mut operations = Vec::new();
+    match Parser::parse_sql(sql_dialect, input) {
+        Ok(statements) => {
+            for statement in statements {
+                let sql = statement.to_string();
+                let parsed = statement.clone();
+                match statement {
+                    // Update to handle more options later
+                    Statement::CreateTable(create_table) => {
+                        let object_name = create_table.name.0;
+                        if object_name.len() > 1 {
+                            say!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
+                            continue;
+                        }
+                        let table_name = object_name[0].value.clone(); // Get the table name
+
+                        let columns_definition: Vec<ColumnDefinition> = create_table
+                            .columns
+                            .iter()
+                            .map(|column| {
+                                let column_type = match &column.data_type {
+                                    sqlparser::ast::DataType::Integer(_) => "INTEGER".to_string(),
+                                    sqlparser::ast::DataType::Text => "TEXT".to_string(),
+                                    _ => "UNKNOWN".to_string(), // Handle other types as necessary
+                                };
+
+                                let options = column
+                                    .options
+                                    .iter()
+                                    .map(|opt| match opt.option {
+                                        ColumnOption::NotNull => "NOT NULL".to_string(),
+                                        ColumnOption::Unique {
+                                            is_primary,
+                                            .. // Bahahaha I'll do characteristics later
+                                        } => {
+                                            if is_primary {
+                                                "PRIMARY KEY".to_string()
+                                            } else {
+                                                "UNIQUE".to_string()
                                             }
-                                            _ => String::new(),
-                                        })
-                                        .filter(|opt| !opt.is_empty()) // Filter out empty options
-                                        .collect();
-
-                                    ColumnDefinition {
-                                        name: column.name.value.clone(),
-                                        column_type,
-                                        options,
-                                    }
-                                })
-                                .collect();
+                                        }
+                                        _ => String::new(),
+                                    })
+                                    .filter(|opt| !opt.is_empty()) // Filter out empty options
+                                    .collect();
 
-                            // Call create_table with the extracted information
-                            if let Err(e) = db.create_table(&table_name, &columns_definition) {
-                                println!("Error creating table: {}", e);
-                            }
+                                ColumnDefinition {
+                                    name: column.name.value.clone(),
+                                    column_type,
+                                    options,
+                                }
+                            })
+                            .collect();
+
+                        let mut operation =
+                            PendingOperation::new(StatementKind::Create, &table_name);
+                        operation.sql = sql;
+                        operation.statement = Some(parsed);
+                        operation.columns = columns_definition;
+                        operations.push(operation);
+                    }
+                    // Update to handle more options later
+                    Statement::Insert(insert) => {
+                        let object_name = insert.table_name.0;
+
+                        // Check if the table exists
+                        if object_name.len() > 1 {
+                            say!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
+                            continue;
                         }
-                        // Update to handle more options later
-                        Statement::Insert(insert) => {
-                            let object_name = insert.table_name.0;
 
-                            // Check if the table exists
-                            if object_name.len() > 1 {
-                                println!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
-                                continue;
-                            }
+                        let table_name = object_name[0].clone();
 
-                            let table_name = object_name[0].clone();
+                        let data = insert.source;
+                        if let Some(data) = data {
+                            let body = data.body;
 
-                            // Check if the table exists
-                            if !db.table_exists(&table_name.value).unwrap_or(false) {
-                                println!("Error: Table '{}' does not exist.", table_name.value);
-                                continue;
-                            }
+                            match *body {
+                                sqlparser::ast::SetExpr::Values(values) => {
+                                    let instances = values.rows;
 
-                            let data = insert.source;
-                            if let Some(data) = data {
-                                let body = data.body;
-
-                                match *body {
-                                    sqlparser::ast::SetExpr::Values(values) => {
-                                        let instances = values.rows;
-
-                                        for instance in instances {
-                                            let mut insert_values: Vec<String> = Vec::new(); // or Vec<sqlparser::ast::Value> if you want to keep the original types
-
-                                            for value in instance {
-                                                match value {
-                                                    sqlparser::ast::Expr::Value(value) => {
-                                                        match value {
-                                                            sqlparser::ast::Value::SingleQuotedString(s) => {
-                                                                insert_values.push(s.clone());
-                                                            }
-                                                            sqlparser::ast::Value::Number(n, _) => {
-                                                                insert_values.push(n.clone());
-                                                            }
-                                                            // Add other cases if needed
-                                                            _ => {
-                                                                println!("Unimplemented value:\n {:?}", value);
-                                                            }
+                                    for (statement_part, instance) in
+                                        instances.into_iter().enumerate()
+                                    {
+                                        let mut insert_values: Vec<String> = Vec::new(); // or Vec<sqlparser::ast::Value> if you want to keep the original types
+
+                                        for value in instance {
+                                            match value {
+                                                sqlparser::ast::Expr::Value(value) => {
+                                                    match value {
+                                                        sqlparser::ast::Value::SingleQuotedString(s) => {
+                                                            insert_values.push(s.clone());
+                                                        }
+                                                        sqlparser::ast::Value::Number(n, _) => {
+                                                            insert_values.push(n.clone());
+                                                        }
+                                                        // Add other cases if needed
+                                                        _ => {
+                                                            say!("Unimplemented value:\n {:?}", value);
                                                         }
-                                                    }
-                                                    _ => {
-                                                        println!(
-                                                            "Unimplemented expression:\n {:?}",
-                                                            value
-                                                        );
                                                     }
                                                 }
-                                            }
-
-                                            // Now just insert the collected values into the database
-                                            if let Err(e) =
-                                                db.insert(&table_name.value, insert_values)
-                                            {
-                                                println!("Insert error: {}", e);
+                                                _ => {
+                                                    say!("Unimplemented expression:\n {:?}", value);
+                                                }
                                             }
                                         }
-                                    }
-                                    _ => {
-                                        println!("Unimplemented body:\n {:?}", body);
+
+                                        // Now just insert the collected values into the database
+                                        let mut operation = PendingOperation::new(
+                                            StatementKind::Insert,
+                                            &table_name.value,
+                                        );
+                                        operation.sql = sql.clone();
+                                        operation.statement = Some(parsed.clone());
+                                        operation.values = insert_values;
+                                        operation.statement_part = statement_part;
+                                        operations.push(operation);
                                     }
                                 }
+                                _ => {
+                                    say!("Unimplemented body:\n {:?}", body);
+                                }
                             }
                         }
-                        // Update to handle more options later
-                        Statement::Query(query) => {
-                            let body = query.body;
-                            if let sqlparser::ast::SetExpr::Select(select) = *body {
-                                let projection = select.projection;
-                                let from = select.from;
-                                let selection = select.selection;
-
-                                if !from.is_empty() {
-                                    let table_name = match &from[0].relation {
-                                        sqlparser::ast::TableFactor::Table {
-                                            name, alias, ..
-                                        } => {
-                                            let object_name = &name.0;
-                                            if object_name.len() > 1 {
-                                                println!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
-                                                continue;
-                                            }
-                                            object_name[0].clone().value
-                                        }
-                                        _ => {
-                                            println!("Unimplemented table:\n {:?}", from[0]);
+                    }
+                    // Update to handle more options later
+                    Statement::Query(query) => {
+                        let body = query.body;
+                        if let sqlparser::ast::SetExpr::Select(select) = *body {
+                            let projection = select.projection;
+                            let from = select.from;
+                            let selection = select.selection;
+
+                            if !from.is_empty() {
+                                let table_name = match &from[0].relation {
+                                    sqlparser::ast::TableFactor::Table { name, .. } => {
+                                        let object_name = &name.0;
+                                        if object_name.len() > 1 {
+                                            say!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
                                             continue;
                                         }
-                                    };
-
-                                    // Check if the table exists
-                                    if !db.table_exists(&table_name).unwrap_or(false) {
-                                        println!("Error: Table '{}' does not exist.", table_name);
+                                        object_name[0].clone().value
+                                    }
+                                    _ => {
+                                        say!("Unimplemented table:\n {:?}", from[0]);
                                         continue;
                                     }
+                                };
 
-                                    let columns = projection
-                                        .iter()
-                                        .map(|col| col.to_string())
-                                        .collect::<Vec<String>>()
-                                        .join(", ");
-
-                                    let mut where_clauses = Vec::new();
-                                    if let Some(ref selection) = selection {
-                                        match selection {
-                                            sqlparser::ast::Expr::BinaryOp { left, op, right } => {
-                                                let column = match **left {
-                                                    sqlparser::ast::Expr::Identifier(ref id) => {
-                                                        let column = id.value.clone();
-                                                        column
-                                                    }
-                                                    _ => {
-                                                        println!(
-                                                            "Unimplemented selection:\n {:?}",
-                                                            selection
-                                                        );
-                                                        continue;
+                                let columns = projection
+                                    .iter()
+                                    .map(|col| col.to_string())
+                                    .collect::<Vec<String>>()
+                                    .join(", ");
+
+                                let mut where_clauses = Vec::new();
+                                if let Some(ref selection) = selection {
+                                    match selection {
+                                        sqlparser::ast::Expr::BinaryOp { left, right, .. } => {
+                                            let column = match **left {
+                                                sqlparser::ast::Expr::Identifier(ref id) => {
+                                                    id.value.clone()
+                                                }
+                                                _ => {
+                                                    say!(
+                                                        "Unimplemented selection:\n {:?}",
+                                                        selection
+                                                    );
+                                                    continue;
+                                                }
+                                            };
+                                            let value = match **right {
+                                                sqlparser::ast::Expr::Value(ref value) => match value {
+                                                    sqlparser::ast::Value::SingleQuotedString(s) => {
+                                                        s.clone()
                                                     }
-                                                };
-                                                let value = match **right {
-                                                    sqlparser::ast::Expr::Value(ref value) => match value {
-                                                        sqlparser::ast::Value::SingleQuotedString(s) => {
-                                                            s.clone()
-                                                        }
-                                                        sqlparser::ast::Value::Number(n, _) => n.clone(),
-                                                        _ => {
-                                                            println!("Unimplemented value:\n {:?}", value);
-                                                            continue;
-                                                        }
-                                                    },
+                                                    sqlparser::ast::Value::Number(n, _) => n.clone(),
                                                     _ => {
-                                                        println!(
-                                                            "Unimplemented selection:\n {:?}",
-                                                            selection
-                                                        );
+                                                        say!("Unimplemented value:\n {:?}", value);
                                                         continue;
                                                     }
-                                                };
-                                                where_clauses.push(Assignment {
-                                                    target: sqlparser::ast::AssignmentTarget::ColumnName(
-                                                        ObjectName {
-                                                            0: vec![Ident {
-                                                                value: column,
-                                                                quote_style: None,
-                                                            }],
-                                                        },
+                                                },
+                                                _ => {
+                                                    say!(
+                                                        "Unimplemented selection:\n {:?}",
+                                                        selection
+                                                    );
+                                                    continue;
+                                                }
+                                            };
+                                            where_clauses.push(Assignment {
+                                                target:
+                                                    sqlparser::ast::AssignmentTarget::ColumnName(
+                                                        ObjectName(vec![Ident {
+                                                            value: column,
+                                                            quote_style: None,
+                                                        }]),
                                                     ),
-                                                    value: sqlparser::ast::Expr::Value(
-                                                        sqlparser::ast::Value::SingleQuotedString(value),
+                                                value: sqlparser::ast::Expr::Value(
+                                                    sqlparser::ast::Value::SingleQuotedString(
+                                                        value,
                                                     ),
-                                                });
-                                            }
-                                            _ => {
-                                                println!(
-                                                    "Unimplemented selection:\n {:?}",
-                                                    selection
-                                                );
-                                            }
+                                                ),
+                                            });
+                                        }
+                                        _ => {
+                                            say!("Unimplemented selection:\n {:?}", selection);
                                         }
                                     }
-                                    db.select(&table_name, &columns, &where_clauses);
                                 }
+                                let mut operation =
+                                    PendingOperation::new(StatementKind::Select, &table_name);
+                                operation.sql = sql;
+                                operation.statement = Some(parsed);
+                                operation.projection = columns;
+                                operation.where_clauses = where_clauses;
+                                operations.push(operation);
                             }
                         }
-                        // Update to handle more options later
-                        Statement::Update {
-                            table,
-                            assignments,
-                            from,
-                            selection,
-                            returning,
-                        } => {
-                            let table_name = match table.relation {
-                                sqlparser::ast::TableFactor::Table { name, alias, .. } => {
-                                    let object_name = name.0;
-                                    if object_name.len() > 1 {
-                                        println!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
-                                        continue;
-                                    }
-                                    object_name[0].clone().value
-                                }
-                                _ => {
-                                    println!("Unimplemented table:\n {:?}", table);
+                    }
+                    // Update to handle more options later
+                    Statement::Update {
+                        table,
+                        assignments,
+                        selection,
+                        ..
+                    } => {
+                        let table_name = match table.relation {
+                            sqlparser::ast::TableFactor::Table { name, .. } => {
+                                let object_name = name.0;
+                                if object_name.len() > 1 {
+                                    say!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
                                     continue;
                                 }
-                            };
-
-                            // Check if the table exists
-                            if !db.table_exists(&table_name).unwrap_or(false) {
-                                println!("Error: Table '{}' does not exist.", table_name);
+                                object_name[0].clone().value
+                            }
+                            _ => {
+                                say!("Unimplemented table:\n {:?}", table);
                                 continue;
                             }
+                        };
 
-                            let mut where_clauses = Vec::new();
-                            if let Some(ref selection) = selection {
-                                match selection {
-                                    sqlparser::ast::Expr::BinaryOp { left, op, right } => {
-                                        let column = match **left {
-                                            sqlparser::ast::Expr::Identifier(ref id) => {
-                                                let column = id.value.clone();
-                                                column
-                                            }
-                                            _ => {
-                                                println!(
-                                                    "Unimplemented selection:\n {:?}",
-                                                    selection
-                                                );
-                                                continue;
+                        let mut where_clauses = Vec::new();
+                        if let Some(ref selection) = selection {
+                            match selection {
+                                sqlparser::ast::Expr::BinaryOp { left, right, .. } => {
+                                    let column = match **left {
+                                        sqlparser::ast::Expr::Identifier(ref id) => {
+                                            id.value.clone()
+                                        }
+                                        _ => {
+                                            say!("Unimplemented selection:\n {:?}", selection);
+                                            continue;
+                                        }
+                                    };
+                                    let value = match **right {
+                                        sqlparser::ast::Expr::Value(ref value) => match value {
+                                            sqlparser::ast::Value::SingleQuotedString(s) => {
+                                                s.clone()
                                             }
-                                        };
-                                        let value = match **right {
-                                            sqlparser::ast::Expr::Value(ref value) => match value {
-                                                sqlparser::ast::Value::SingleQuotedString(s) => {
-                                                    s.clone()
-                                                }
-                                                sqlparser::ast::Value::Number(n, _) => n.clone(),
-                                                _ => {
-                                                    println!("Unimplemented value:\n {:?}", value);
-                                                    continue;
-                                                }
-                                            },
+                                            sqlparser::ast::Value::Number(n, _) => n.clone(),
                                             _ => {
-                                                println!(
-                                                    "Unimplemented selection:\n {:?}",
-                                                    selection
-                                                );
+                                                say!("Unimplemented value:\n {:?}", value);
                                                 continue;
                                             }
-                                        };
-                                        where_clauses.push(Assignment {
-                                            target: sqlparser::ast::AssignmentTarget::ColumnName(
-                                                ObjectName {
-                                                    0: vec![Ident {
-                                                        value: column,
-                                                        quote_style: None,
-                                                    }],
-                                                },
-                                            ),
-                                            value: sqlparser::ast::Expr::Value(
-                                                sqlparser::ast::Value::SingleQuotedString(value),
-                                            ),
-                                        });
-                                    }
-                                    _ => {
-                                        println!("Unimplemented selection:\n {:?}", selection);
-                                    }
-                                }
-                            }
-                            db.update(&table_name, &assignments, &where_clauses);
-                        }
-                        // Update to handle more options later
-                        Statement::Drop {
-                            object_type, names, ..
-                        } => {
-                            println!("{} {:?}", object_type, names);
-                            for name in names {
-                                let object_name = &name.0;
-                                if object_name.len() > 1 {
-                                    println!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
-                                    return;
+                                        },
+                                        _ => {
+                                            say!("Unimplemented selection:\n {:?}", selection);
+                                            continue;
+                                        }
+                                    };
+                                    where_clauses.push(Assignment {
+                                        target: sqlparser::ast::AssignmentTarget::ColumnName(
+                                            ObjectName(vec![Ident {
+                                                value: column,
+                                                quote_style: None,
+                                            }]),
+                                        ),
+                                        value: sqlparser::ast::Expr::Value(
+                                            sqlparser::ast::Value::SingleQuotedString(value),
+                                        ),
+                                    });
                                 }
-                                let table_name = object_name[0].clone().value;
-
-                                if let Err(e) =
-                                    db.conn.execute(&format!("DROP TABLE {}", table_name), [])
-                                {
-                                    println!("{}", e);
+                                _ => {
+                                    say!("Unimplemented selection:\n {:?}", selection);
                                 }
                             }
                         }
-                        _ => {
-                            println!("Unimplemented statement:\n {:?}", statement);
+                        let mut operation =
+                            PendingOperation::new(StatementKind::Update, &table_name);
+                        operation.sql = sql;
+                        operation.statement = Some(parsed);
+                        operation.assignments = assignments;
+                        operation.where_clauses = where_clauses;
+                        operations.push(operation);
+                    }
+                    // Update to handle more options later
+                    Statement::Drop { names, .. } => {
+                        let mut statement_part = 0;
+                        for name in names {
+                            let object_name = &name.0;
+                            if object_name.len() > 1 {
+                                say!("Sorry, WackyDB doesn't do schemas or databases! Only tables are allowed in this land.");
+                                continue;
+                            }
+                            let table_name = object_name[0].clone().value;
+
+                            let mut operation =
+                                PendingOperation::new(StatementKind::Drop, &table_name);
+                            operation.sql = sql.clone();
+                            operation.statement = Some(parsed.clone());
+                            operation.statement_part = statement_part;
+                            operations.push(operation);
+                            statement_part += 1;
                         }
                     }
+                    _ => {
+                        say!("Unimplemented statement:\n {:?}", statement);
+                    }
                 }
             }
-            Err(e) => {
-                println!("Parsing error huh. This is what happened:\n{}", e);
-            }
         }
+        Err(e) => {
+            say!("Parsing error huh. This is what happened:\n{}", e);
+        }
+    }
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chaos::scripted::{AlwaysEffect, AlwaysProceed, ScriptedChaos};
+    use rows::fixtures;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn in_memory(chaos_source: impl ChaosSource + 'static) -> WackyDB {
+        WackyDB::new(IN_MEMORY_DB_NAME, 42, Box::new(chaos_source)).unwrap()
+    }
+
+    // Runs every operation the input plans out, with the error of each one that failed
+    fn run(db: &mut WackyDB, sql: &str) -> Vec<Result<(), String>> {
+        plan_sql(&GenericDialect {}, sql)
+            .into_iter()
+            .map(|operation| db.execute(operation).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    fn run_ok(db: &mut WackyDB, sql: &str) {
+        for result in run(db, sql) {
+            result.unwrap();
+        }
+    }
+
+    fn with_pets(chaos_source: impl ChaosSource + 'static) -> WackyDB {
+        let db = in_memory(chaos_source);
+        fixtures::add_pets(&db.conn);
+        db
+    }
+
+    fn names(db: &WackyDB) -> Vec<String> {
+        rows::fetch_rows(&db.conn, "pets", "1 = 1")
+            .unwrap()
+            .into_iter()
+            .map(|(_, data)| data["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn in_memory_databases_are_allowed_but_odd_names_are_not() {
+        assert_eq!(sanitize_db_name(":memory:"), Ok(":memory:"));
+        assert!(sanitize_db_name("../evil.db").is_err());
+        assert!(sanitize_db_name("").is_err());
+    }
+
+    #[test]
+    fn create_table_makes_the_table() {
+        let mut db = in_memory(AlwaysProceed);
+        run_ok(
+            &mut db,
+            "CREATE TABLE pets (id INTEGER PRIMARY KEY, name TEXT)",
+        );
+
+        assert!(db.table_exists("pets").unwrap());
+        assert_eq!(
+            rows::column_names(&db.conn, "pets").unwrap(),
+            vec!["id", "name"]
+        );
+    }
+
+    #[test]
+    fn create_table_refuses_a_table_that_exists() {
+        let mut db = with_pets(AlwaysProceed);
+        let results = run(&mut db, "CREATE TABLE pets (id INTEGER)");
+
+        assert_eq!(
+            results,
+            vec![Err("Table 'pets' already exists.".to_string())]
+        );
+    }
+
+    #[test]
+    fn create_table_keeps_users_out_of_internal_tables() {
+        let mut db = in_memory(AlwaysProceed);
+        let results = run(&mut db, "CREATE TABLE _wacky_mine (id INTEGER)");
+
+        assert!(results[0]
+            .as_ref()
+            .unwrap_err()
+            .contains("belong to WackyDB"));
+    }
+
+    #[test]
+    fn insert_adds_every_row_and_checksums_them() {
+        let mut db = in_memory(AlwaysProceed);
+        run_ok(
+            &mut db,
+            "CREATE TABLE pets (id INTEGER PRIMARY KEY, name TEXT)",
+        );
+        run_ok(
+            &mut db,
+            "INSERT INTO pets VALUES (1, 'rex'), (2, 'tom'), (3, 'kit')",
+        );
+
+        assert_eq!(names(&db), vec!["rex", "tom", "kit"]);
+        assert!(bitrot::scrub(&db.conn, Some("pets")).unwrap().is_empty());
+        let checksums: i64 = db
+            .conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {}", bitrot::CHECKSUM_TABLE),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(checksums, 3);
+    }
+
+    #[test]
+    fn insert_into_a_missing_table_fails() {
+        let mut db = in_memory(AlwaysProceed);
+        let results = run(&mut db, "INSERT INTO ghosts VALUES (1)");
+
+        assert_eq!(
+            results,
+            vec![Err("Table 'ghosts' does not exist.".to_string())]
+        );
+    }
+
+    #[test]
+    fn select_remembers_what_it_found() {
+        let mut db = with_pets(AlwaysProceed);
+        run_ok(&mut db, "SELECT name FROM pets WHERE id = 2");
+
+        let answer = db.last_answer.as_ref().unwrap();
+        assert_eq!(answer.truth.columns, vec!["name"]);
+        assert_eq!(
+            answer.truth.rows,
+            vec![vec![rusqlite::types::Value::Text("tom".to_string())]]
+        );
+        assert!(answer.shown.lie.is_none());
+    }
+
+    #[test]
+    fn update_rewrites_matching_rows_and_their_checksums() {
+        let mut db = with_pets(AlwaysProceed);
+        run_ok(&mut db, "UPDATE pets SET name = 'max' WHERE id = 1");
+
+        assert_eq!(names(&db), vec!["max", "tom", "kit"]);
+        assert!(bitrot::scrub(&db.conn, Some("pets")).unwrap().is_empty());
+    }
+
+    fn checksums(db: &WackyDB) -> i64 {
+        db.conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {}", bitrot::CHECKSUM_TABLE),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn drop_table_takes_its_checksums_along() {
+        let mut db = with_pets(AlwaysProceed);
+        run_ok(&mut db, "DROP TABLE pets");
+
+        assert!(!db.table_exists("pets").unwrap());
+        assert_eq!(checksums(&db), 0);
+    }
+
+    #[test]
+    fn deleted_rows_take_their_checksums_along() {
+        let mut db = with_pets(AlwaysProceed);
+        trash::trash_matching_rows(&db.conn, "pets", "id = 3", 100).unwrap();
+        assert_eq!(checksums(&db), 2);
+
+        // A new row that gets the old rowid isn't mistaken for the deleted one
+        run_ok(&mut db, "INSERT INTO pets VALUES (3, 'bo')");
+        assert!(bitrot::scrub(&db.conn, Some("pets")).unwrap().is_empty());
+        assert_eq!(checksums(&db), 3);
+    }
+
+    #[test]
+    fn scrub_prunes_checksums_of_rows_that_are_gone() {
+        let db = with_pets(AlwaysProceed);
+        db.conn
+            .execute("DELETE FROM pets WHERE id = 1", [])
+            .unwrap();
+
+        assert!(bitrot::scrub(&db.conn, None).unwrap().is_empty());
+        assert_eq!(checksums(&db), 2);
+    }
+
+    #[test]
+    fn nothing_happened_turns_the_statement_away() {
+        let mut db = with_pets(AlwaysEffect("nothing_happened".to_string()));
+        let results = run(&mut db, "INSERT INTO pets VALUES (4, 'bo')");
+
+        assert!(results[0]
+            .as_ref()
+            .unwrap_err()
+            .contains("nothing happened"));
+        assert_eq!(db.row_count("pets").unwrap(), 3);
+    }
+
+    #[test]
+    fn fire_aborts_the_statement_and_keeps_burning() {
+        let mut db = with_pets(AlwaysEffect("database_on_fire".to_string()));
+        let results = run(&mut db, "UPDATE pets SET name = 'max' WHERE id = 1");
+
+        assert!(results[0].as_ref().unwrap_err().contains("on fire"));
+        assert_eq!(names(&db), vec!["rex", "tom", "kit"]);
+        let burning = fire::burning_tables(&db.conn).unwrap();
+        assert_eq!(burning[0].0, "pets");
+
+        // A burning table is read-only, whatever chaos has to say about it
+        db.chaos_source = Box::new(AlwaysProceed);
+        let results = run(&mut db, "INSERT INTO pets VALUES (4, 'bo')");
+        assert!(results[0].is_err());
+    }
+
+    fn intensity(db: &WackyDB, table_name: &str) -> u32 {
+        fire::burning_tables(&db.conn)
+            .unwrap()
+            .into_iter()
+            .find(|(name, _)| name == table_name)
+            .map(|(_, intensity)| intensity)
+            .unwrap()
+    }
+
+    #[test]
+    fn fire_spreads_once_a_statement_and_not_for_refused_ones() {
+        let mut db = with_pets(AlwaysProceed);
+        run_ok(
+            &mut db,
+            "CREATE TABLE owners (id INTEGER PRIMARY KEY, name TEXT)",
+        );
+        fire::ignite(&db.conn, "pets").unwrap();
+
+        run_ok(
+            &mut db,
+            "INSERT INTO owners VALUES (1, 'ana'), (2, 'bo'), (3, 'cy')",
+        );
+        assert_eq!(intensity(&db, "pets"), 2);
+
+        let results = run(&mut db, "INSERT INTO ghosts VALUES (1)");
+        assert!(results[0].is_err());
+        assert_eq!(intensity(&db, "pets"), 2);
+    }
+
+    #[test]
+    fn trash_skips_the_insert_but_keeps_the_row() {
+        let mut db = with_pets(AlwaysEffect("data_in_trash".to_string()));
+        let results = run(&mut db, "INSERT INTO pets VALUES (4, 'bo')");
+
+        assert!(results[0].as_ref().unwrap_err().contains("RESTORE"));
+        assert_eq!(db.row_count("pets").unwrap(), 3);
+        assert_eq!(trash::list(&db.conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn trash_turns_away_values_that_dont_fit_the_table() {
+        let mut db = with_pets(AlwaysEffect("data_in_trash".to_string()));
+        let results = run(&mut db, "INSERT INTO pets VALUES (4)");
+
+        assert!(results[0]
+            .as_ref()
+            .unwrap_err()
+            .contains("2 column(s) but 1 value(s)"));
+        assert!(trash::list(&db.conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn trash_stays_out_of_a_burning_table() {
+        let mut db = with_pets(AlwaysEffect("data_in_trash".to_string()));
+        let _ = run(&mut db, "INSERT INTO pets VALUES (4, 'bo')");
+        let id = trash::list(&db.conn).unwrap()[0].id;
+        fire::ignite(&db.conn, "pets").unwrap();
+
+        let error = trash::restore(&db.conn, id, db.difficulty).unwrap_err();
+        assert!(error.to_string().contains("on fire"));
+        assert_eq!(db.row_count("pets").unwrap(), 3);
+
+        // Boring mode doesn't believe in fire
+        db.difficulty = Difficulty::Boring;
+        trash::restore(&db.conn, id, db.difficulty).unwrap();
+        assert_eq!(names(&db), vec!["rex", "tom", "kit", "bo"]);
+    }
+
+    #[test]
+    fn the_prompt_only_shows_fires_that_count() {
+        let mut db = with_pets(AlwaysProceed);
+        fire::ignite(&db.conn, "pets").unwrap();
+        assert!(db.prompt().contains("🔥[pets]"));
+
+        db.difficulty = Difficulty::Boring;
+        assert_eq!(db.prompt(), "wacky_db [boring]> ");
+    }
+
+    #[test]
+    fn undo_chaos_puts_trashed_rows_back() {
+        let mut db = with_pets(AlwaysEffect("data_in_trash".to_string()));
+        let _ = run(&mut db, "SELECT * FROM pets");
+        assert!(db.row_count("pets").unwrap() < 3);
+
+        let snapshot = db.undo_chaos().unwrap().unwrap();
+        assert_eq!(snapshot.effect, "data_in_trash");
+        assert_eq!(names(&db), vec!["rex", "tom", "kit"]);
+        assert!(db.undo_chaos().unwrap().is_none());
+    }
+
+    #[test]
+    fn undo_chaos_keeps_what_happened_since() {
+        let mut db = with_pets(AlwaysEffect("data_in_trash".to_string()));
+        let _ = run(&mut db, "SELECT * FROM pets");
+        db.chaos_source = Box::new(AlwaysProceed);
+        run_ok(&mut db, "INSERT INTO pets VALUES (4, 'bo')");
+
+        db.undo_chaos().unwrap().unwrap();
+        assert_eq!(names(&db), vec!["rex", "tom", "kit", "bo"]);
+        assert!(trash::list(&db.conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn undo_chaos_takes_back_schema_drift() {
+        let mut db = with_pets(AlwaysEffect("schema_drift".to_string()));
+        run_ok(&mut db, "SELECT * FROM pets");
+        db.chaos_source = Box::new(AlwaysProceed);
+
+        db.undo_chaos().unwrap().unwrap();
+        assert_eq!(
+            rows::column_names(&db.conn, "pets").unwrap(),
+            vec!["id", "name"]
+        );
+        assert_eq!(names(&db), vec!["rex", "tom", "kit"]);
+    }
+
+    #[test]
+    fn undo_chaos_leaves_other_tables_alone() {
+        let mut db = with_pets(AlwaysProceed);
+        run_ok(
+            &mut db,
+            "CREATE TABLE owners (id INTEGER PRIMARY KEY, name TEXT)",
+        );
+        let before = snapshot::take(&db.conn, "pets").unwrap();
+        db.conn
+            .execute("DELETE FROM pets WHERE id = 1", [])
+            .unwrap();
+        // Meanwhile, somewhere else, as if by decay or the gremlin
+        run_ok(&mut db, "INSERT INTO owners VALUES (1, 'ana')");
+        db.conn
+            .execute(
+                &format!(
+                    "INSERT INTO {} (table_name, row_data) VALUES ('owners', '{{}}')",
+                    decay::DECAY_LOG_TABLE
+                ),
+                [],
+            )
+            .unwrap();
+        let changes = before.finish(&db.conn).unwrap();
+        db.snapshots
+            .push(Snapshot::new("data_in_trash", "", "pets", changes));
+
+        db.undo_chaos().unwrap().unwrap();
+        assert_eq!(names(&db), vec!["rex", "tom", "kit"]);
+        assert_eq!(db.row_count("owners").unwrap(), 1);
+        assert_eq!(decay::log(&db.conn).unwrap().len(), 1);
+    }
+
+    // Proceeds as normal, writing down the odds it was asked to roll at
+    struct OddsTaken(Rc<RefCell<Vec<f64>>>);
+
+    impl ChaosSource for OddsTaken {
+        fn pick(
+            &mut self,
+            profile: &ChaosProfile,
+            kind: StatementKind,
+            _: &mut StdRng,
+            frequency: u32,
+        ) -> Option<String> {
+            self.0
+                .borrow_mut()
+                .push(profile.chaos_chance(kind, frequency));
+            None
+        }
+    }
+
+    #[test]
+    fn a_multi_row_insert_rolls_at_the_odds_explain_chaos_gives() {
+        let odds = Rc::new(RefCell::new(Vec::new()));
+        let mut db = with_pets(OddsTaken(Rc::clone(&odds)));
+        let insert = "INSERT INTO pets VALUES (4, 'bo'), (5, 'jo'), (6, 'mo'), (7, 'lo')";
+
+        // What EXPLAIN CHAOS works out
+        let operation = &plan_sql(&GenericDialect {}, insert)[0];
+        let risk = risk::assess(&db.conn, operation).unwrap();
+        assert_eq!(risk.rows_at_stake, 4);
+        let explained = db
+            .chaos_profile
+            .chaos_chance(operation.kind, db.chaos_frequency(&risk));
+
+        run_ok(&mut db, insert);
+        assert_eq!(*odds.borrow(), vec![explained]);
+    }
+
+    #[test]
+    fn scripted_chaos_plays_out_in_order() {
+        let mut db = with_pets(ScriptedChaos::new([None, Some("hide_rows")]));
+
+        run_ok(&mut db, "SELECT * FROM pets");
+        assert!(db.last_answer.as_ref().unwrap().shown.lie.is_none());
+
+        run_ok(&mut db, "SELECT * FROM pets");
+        let answer = db.last_answer.as_ref().unwrap();
+        assert!(answer.shown.lie.is_some());
+        assert!(answer.shown.rows.len() < answer.truth.rows.len());
+
+        // The script has run out, so it's back to the truth
+        run_ok(&mut db, "SELECT * FROM pets");
+        assert!(db.last_answer.as_ref().unwrap().shown.lie.is_none());
+    }
+
+    #[test]
+    fn boring_mode_ignores_the_chaos_source() {
+        let mut db = with_pets(AlwaysEffect("database_on_fire".to_string()));
+        db.difficulty = Difficulty::Boring;
+        run_ok(&mut db, "INSERT INTO pets VALUES (4, 'bo')");
+
+        assert_eq!(db.row_count("pets").unwrap(), 4);
+        assert!(fire::burning_tables(&db.conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_game_that_wouldnt_start_costs_nothing() {
+        // Tests have no terminal, so no game ever starts
+        let mut db = with_pets(AlwaysEffect("gaming_time".to_string()));
+        run_ok(&mut db, "INSERT INTO pets VALUES (4, 'bo')");
+
+        assert_eq!(names(&db), vec!["rex", "tom", "kit", "bo"]);
+        assert!(leaderboard::top(&db.conn, None, 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_game_that_wouldnt_start_replays_the_same() {
+        // Tests have no terminal, so no game ever starts
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("wacky_forfeit_{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let copy = dir.join(format!("wacky_forfeit_{}.sqlite", std::process::id()));
+        let insert = "INSERT INTO pets VALUES (4, 'bo')";
+
+        let mut db = with_pets(AlwaysEffect("gaming_time".to_string()));
+        let header = SessionHeader {
+            seed: db.seed,
+            difficulty: db.difficulty.to_string(),
+            chaos_profile: db.chaos_profile.clone(),
+            player: db.player.clone(),
+            database: session::database_copy(path),
+        };
+        session::record(path, &db.conn, header).unwrap();
+        session::begin_step(insert);
+        assert!(run(&mut db, insert)[0].is_ok());
+        session::end_step().unwrap();
+        assert_eq!(session::finish(), None);
+
+        let mut db = with_pets(AlwaysEffect("gaming_time".to_string()));
+        session::replay(path, copy.to_str().unwrap()).unwrap();
+        session::begin_step(insert);
+        let _ = run(&mut db, insert);
+        session::end_step().unwrap();
+        assert_eq!(session::finish(), Some((1, 0)));
+
+        for file in [path.to_string(), session::database_copy(path)] {
+            let _ = std::fs::remove_file(file);
+        }
+        let _ = std::fs::remove_file(copy);
+    }
+
+    #[test]
+    fn chaos_is_journaled() {
+        let mut db = with_pets(AlwaysEffect("nothing_happened".to_string()));
+        let _ = run(&mut db, "SELECT * FROM pets");
+
+        let entries = journal::entries(&db.conn, None).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].effect, "nothing_happened");
+        assert_eq!(entries[0].table_name, "pets");
     }
 }
@@ -0,0 +1,189 @@
+use crate::chaos::{PendingOperation, StatementKind};
+use crate::difficulty::Difficulty;
+use crate::session::say;
+use crate::{drift, fire, is_internal_table, risk, WackyDB, INTERNAL_TABLE_PREFIX};
+
+// One step every statement goes through on its way to the database. `before` runs in
+// pipeline order and can refuse the statement with an error. `after` runs in reverse order
+// once the statement has run or been refused, and sees how it went.
+pub trait Stage {
+    fn before(
+        &self,
+        _db: &mut WackyDB,
+        _operation: &mut PendingOperation,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn after(
+        &self,
+        _db: &mut WackyDB,
+        _operation: &PendingOperation,
+        _result: &Result<(), Box<dyn std::error::Error>>,
+    ) {
+    }
+}
+
+pub fn default_stages() -> Vec<Box<dyn Stage>> {
+    vec![
+        Box::new(LoggingStage),
+        Box::new(ValidationStage),
+        Box::new(TickStage),
+        Box::new(ChaosStage),
+        Box::new(LatencyStage),
+    ]
+}
+
+pub fn run(
+    stages: &[Box<dyn Stage>],
+    db: &mut WackyDB,
+    operation: &mut PendingOperation,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let result = stages
+        .iter()
+        .try_for_each(|stage| stage.before(db, operation))
+        .and_then(|()| db.run_operation(operation));
+
+    for stage in stages.iter().rev() {
+        stage.after(db, operation, &result);
+    }
+
+    result
+}
+
+// Tells the user when a statement didn't make it
+pub struct LoggingStage;
+
+impl Stage for LoggingStage {
+    fn after(
+        &self,
+        _db: &mut WackyDB,
+        operation: &PendingOperation,
+        result: &Result<(), Box<dyn std::error::Error>>,
+    ) {
+        if let Err(e) = result {
+            match operation.kind {
+                StatementKind::Create => say!("Error creating table: {}", e),
+                StatementKind::Insert => say!("Insert error: {}", e),
+                StatementKind::Select => say!("Select error: {}", e),
+                StatementKind::Update => say!("Update error: {}", e),
+                StatementKind::Drop => say!("Drop error: {}", e),
+            }
+        }
+    }
+}
+
+// Time passes with every statement that makes it past validation, letting fires do their
+// thing. Once a statement, however many rows or tables it has.
+pub struct TickStage;
+
+impl Stage for TickStage {
+    fn before(
+        &self,
+        db: &mut WackyDB,
+        operation: &mut PendingOperation,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if operation.statement_part == 0 {
+            db.tick(&operation.sql);
+        }
+        Ok(())
+    }
+}
+
+// Makes sure the statement makes sense before any chaos gets involved
+pub struct ValidationStage;
+
+impl Stage for ValidationStage {
+    fn before(
+        &self,
+        db: &mut WackyDB,
+        operation: &mut PendingOperation,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let table_name = operation.table_name.clone();
+
+        if operation.kind == StatementKind::Create {
+            if is_internal_table(&table_name) {
+                return Err(format!(
+                    "Tables starting with '{}' belong to WackyDB. Get your own!",
+                    INTERNAL_TABLE_PREFIX
+                )
+                .into());
+            }
+            if db.table_exists(&table_name)? {
+                return Err(format!("Table '{}' already exists.", table_name).into());
+            }
+            return Ok(());
+        }
+
+        if !db.table_exists(&table_name)? {
+            match drift::resolve_table(&db.conn, &table_name)? {
+                Some(current) if db.table_exists(&current)? => {
+                    forgive_drift(db, &table_name, &current)?;
+                    operation.table_name = current;
+                }
+                _ => return Err(format!("Table '{}' does not exist.", table_name).into()),
+            }
+        }
+        for (old, new) in drift::resolve_columns(&db.conn, operation)? {
+            forgive_drift(
+                db,
+                &format!("{}.{}", operation.table_name, old),
+                &format!("{}.{}", operation.table_name, new),
+            )?;
+        }
+
+        if operation.kind != StatementKind::Select {
+            fire::guard_write_unless_boring(&db.conn, &operation.table_name, db.difficulty)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Old names still work after schema drift, with a warning. On nightmare they don't.
+fn forgive_drift(db: &WackyDB, old: &str, new: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if db.difficulty == Difficulty::Nightmare {
+        return Err(format!(
+            "'{}'? Never heard of it. There's a '{}' though, if that rings a bell. 🙃",
+            old, new
+        )
+        .into());
+    }
+
+    say!(
+        "⚠️  '{}' is called '{}' now. I'll let it slide, but your queries are drifting behind the schema.",
+        old, new
+    );
+    Ok(())
+}
+
+// Sizes up what the operation puts at stake, then lets chaos have its roll
+pub struct ChaosStage;
+
+impl Stage for ChaosStage {
+    fn before(
+        &self,
+        db: &mut WackyDB,
+        operation: &mut PendingOperation,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // The rest of a multi-row INSERT rides on the roll its first row got
+        if operation.kind == StatementKind::Insert && operation.statement_part > 0 {
+            return Ok(());
+        }
+        let risk = risk::assess(&db.conn, operation)?;
+        db.chaos_engine(operation, &risk)
+    }
+}
+
+pub struct LatencyStage;
+
+impl Stage for LatencyStage {
+    fn before(
+        &self,
+        db: &mut WackyDB,
+        operation: &mut PendingOperation,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        db.slow_down(&operation.table_name);
+        Ok(())
+    }
+}
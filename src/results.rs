@@ -0,0 +1,81 @@
+use std::fmt;
+
+use rusqlite::types::Value;
+use rusqlite::Connection;
+
+// The rows a SELECT came back with, held on to so chaos can lie about them before anyone
+// sees them
+#[derive(Clone)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    // What was done to the rows, if they're a lie
+    pub lie: Option<String>,
+}
+
+impl ResultSet {
+    pub fn query(conn: &Connection, sql: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut stmt = conn.prepare(sql)?;
+        let columns: Vec<String> = stmt
+            .column_names()
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        let mut rows = Vec::new();
+        let mut result = stmt.query([])?;
+        while let Some(row) = result.next()? {
+            rows.push(
+                (0..columns.len())
+                    .map(|i| row.get(i))
+                    .collect::<Result<Vec<Value>, _>>()?,
+            );
+        }
+
+        Ok(Self {
+            columns,
+            rows,
+            lie: None,
+        })
+    }
+}
+
+// One line per row, as column -> (type, value)
+impl fmt::Display for ResultSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.rows.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:?}", RowView(&self.columns, row))?;
+        }
+
+        Ok(())
+    }
+}
+
+struct RowView<'a>(&'a [String], &'a [Value]);
+
+impl fmt::Debug for RowView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for (column, value) in self.0.iter().zip(self.1) {
+            map.key(column);
+            match value {
+                Value::Null => map.value(&(value.data_type(), ())),
+                Value::Integer(i) => map.value(&(value.data_type(), i)),
+                Value::Real(r) => map.value(&(value.data_type(), r)),
+                Value::Text(s) => map.value(&(value.data_type(), s)),
+                Value::Blob(b) => map.value(&(value.data_type(), b.len())),
+            };
+        }
+        map.finish()
+    }
+}
+
+// The last SELECT, both as it really was and as it was shown
+pub struct Answer {
+    pub statement: String,
+    pub truth: ResultSet,
+    pub shown: ResultSet,
+}
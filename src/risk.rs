@@ -0,0 +1,233 @@
+use std::fmt;
+
+use rusqlite::Connection;
+use sqlparser::ast::{AssignmentTarget, BinaryOperator, Expr, SetExpr, Statement};
+
+use crate::chaos::{PendingOperation, StatementKind};
+use crate::rows;
+
+// An everyday statement touching a single row
+pub const BASELINE: i32 = 100;
+const MIN_SCORE: i32 = 25;
+const MAX_SCORE: i32 = 400;
+
+// How much a statement has riding on it. The score is a percentage chaos frequency is scaled
+// by, so the more data a statement can hurt, the likelier chaos is to show up for it.
+pub struct Risk {
+    // Rows the statement writes, reads or takes down with it
+    pub rows_at_stake: usize,
+    // What went into the score, and how much each thing added or took away
+    pub factors: Vec<(String, i32)>,
+    pub score: u32,
+}
+
+impl Risk {
+    fn new(rows_at_stake: usize) -> Self {
+        Self {
+            rows_at_stake,
+            factors: Vec::new(),
+            score: BASELINE as u32,
+        }
+    }
+
+    fn add(&mut self, factor: impl Into<String>, points: i32) {
+        self.factors.push((factor.into(), points));
+        let total = BASELINE + self.factors.iter().map(|(_, points)| points).sum::<i32>();
+        self.score = total.clamp(MIN_SCORE, MAX_SCORE) as u32;
+    }
+}
+
+impl fmt::Display for Risk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "  {:>5}  an everyday statement", BASELINE)?;
+        for (factor, points) in &self.factors {
+            write!(f, "\n  {:>+5}  {}", points, factor)?;
+        }
+        write!(f, "\n  = {:>3}  risk score", self.score)
+    }
+}
+
+// Sizes up the operation: how many rows it puts at stake and how carefully it picks them
+pub fn assess(
+    conn: &Connection,
+    operation: &PendingOperation,
+) -> Result<Risk, Box<dyn std::error::Error>> {
+    let mut risk = Risk::new(rows_at_stake(conn, operation)?);
+
+    // Every doubling of the rows at stake adds a bit more
+    match risk.rows_at_stake {
+        0 => risk.add("nothing to lose", -50),
+        1 => {}
+        rows => risk.add(format!("{} rows at stake", rows), 15 * rows.ilog2() as i32),
+    }
+
+    match operation.kind {
+        StatementKind::Select | StatementKind::Update => match selection(operation) {
+            None if operation.kind == StatementKind::Update => {
+                risk.add("no WHERE clause, every row gets rewritten", 50)
+            }
+            None => risk.add("no WHERE clause, every row comes along", 10),
+            Some(selection) if is_point_lookup(conn, operation, selection)? => {
+                risk.add("a point lookup by primary key", -25)
+            }
+            Some(_) => {}
+        },
+        StatementKind::Drop => risk.add("the whole table goes with it", 50),
+        StatementKind::Create | StatementKind::Insert => {}
+    }
+
+    if operation.assignments.len() > 1 {
+        risk.add(
+            format!("{} columns rewritten at once", operation.assignments.len()),
+            5 * (operation.assignments.len() as i32 - 1),
+        );
+    }
+
+    Ok(risk)
+}
+
+fn rows_at_stake(
+    conn: &Connection,
+    operation: &PendingOperation,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let count = |where_str: &str| -> Result<usize, Box<dyn std::error::Error>> {
+        let tables = rows::user_tables(conn)?;
+        if !tables
+            .iter()
+            .any(|table| table.eq_ignore_ascii_case(&operation.table_name))
+        {
+            return Ok(0);
+        }
+        let count: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM {} WHERE {}",
+                operation.table_name, where_str
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    };
+
+    match operation.kind {
+        StatementKind::Create => Ok(0),
+        // Each row of a multi-row INSERT runs on its own, but they're all in it together, and
+        // chaos only rolls once for the lot
+        StatementKind::Insert => Ok(match &operation.statement {
+            Some(Statement::Insert(insert)) => match insert.source.as_deref().map(|q| &*q.body) {
+                Some(SetExpr::Values(values)) => values.rows.len(),
+                _ => 1,
+            },
+            _ => 1,
+        }),
+        StatementKind::Select | StatementKind::Update => {
+            count(&crate::where_sql(&operation.where_clauses))
+        }
+        StatementKind::Drop => count("1 = 1"),
+    }
+}
+
+// The WHERE clause as it was parsed
+fn selection(operation: &PendingOperation) -> Option<&Expr> {
+    match operation.statement.as_ref()? {
+        Statement::Query(query) => match &*query.body {
+            SetExpr::Select(select) => select.selection.as_ref(),
+            _ => None,
+        },
+        Statement::Update { selection, .. } => selection.as_ref(),
+        _ => None,
+    }
+}
+
+// `WHERE <primary key> = <value>`, which can only ever hit the one row. The column is taken
+// from the operation, where schema drift has already been caught up with.
+fn is_point_lookup(
+    conn: &Connection,
+    operation: &PendingOperation,
+    selection: &Expr,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if !matches!(
+        selection,
+        Expr::BinaryOp {
+            op: BinaryOperator::Eq,
+            ..
+        }
+    ) {
+        return Ok(false);
+    }
+    let [where_clause] = operation.where_clauses.as_slice() else {
+        return Ok(false);
+    };
+    let AssignmentTarget::ColumnName(column) = &where_clause.target else {
+        return Ok(false);
+    };
+
+    let column = column.to_string();
+    Ok(rows::column_info(conn, &operation.table_name)?
+        .iter()
+        .any(|info| info.primary_key && info.name.eq_ignore_ascii_case(&column)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_sql;
+    use crate::rows::fixtures;
+    use sqlparser::dialect::GenericDialect;
+
+    fn score(conn: &Connection, sql: &str) -> Risk {
+        let operations = plan_sql(&GenericDialect {}, sql);
+        assess(conn, &operations[0]).unwrap()
+    }
+
+    #[test]
+    fn a_point_lookup_is_safer_than_an_everyday_statement() {
+        let conn = fixtures::pets();
+        let risk = score(&conn, "SELECT * FROM pets WHERE id = 2");
+
+        assert_eq!(risk.rows_at_stake, 1);
+        assert!(risk.score < BASELINE as u32);
+    }
+
+    #[test]
+    fn a_lookup_by_anything_else_is_not_a_point_lookup() {
+        let conn = fixtures::pets();
+        let risk = score(&conn, "SELECT * FROM pets WHERE name = 'tom'");
+
+        assert_eq!(risk.score, BASELINE as u32);
+    }
+
+    #[test]
+    fn every_row_of_a_multi_row_insert_is_at_stake() {
+        let conn = fixtures::pets();
+        let single = score(&conn, "INSERT INTO pets VALUES (5, 'ed')");
+        let multi = score(
+            &conn,
+            "INSERT INTO pets VALUES (5, 'ed'), (6, 'al'), (7, 'jo')",
+        );
+
+        assert_eq!(single.rows_at_stake, 1);
+        assert_eq!(multi.rows_at_stake, 3);
+        assert!(multi.score > single.score);
+    }
+
+    #[test]
+    fn an_unfiltered_update_is_riskier_than_a_filtered_one() {
+        let conn = fixtures::pets();
+        let filtered = score(&conn, "UPDATE pets SET name = 'max' WHERE name = 'rex'");
+        let unfiltered = score(&conn, "UPDATE pets SET name = 'max'");
+
+        assert_eq!(unfiltered.rows_at_stake, 3);
+        assert!(unfiltered.score > filtered.score);
+    }
+
+    #[test]
+    fn nothing_at_stake_keeps_the_score_low_but_not_zero() {
+        let conn = fixtures::pets();
+        let risk = score(&conn, "CREATE TABLE cats (id INTEGER)");
+
+        assert_eq!(risk.rows_at_stake, 0);
+        assert_eq!(risk.score, 50);
+        assert!(risk.score >= MIN_SCORE as u32);
+    }
+}
@@ -0,0 +1,184 @@
+use rand::{seq::SliceRandom, Rng};
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use serde_json::{Map, Number};
+
+// A row as column name -> value, the way WackyDB stashes rows away in its own tables
+pub type RowData = Map<String, serde_json::Value>;
+
+pub struct ColumnInfo {
+    pub name: String,
+    pub not_null: bool,
+    pub primary_key: bool,
+}
+
+pub fn column_info(
+    conn: &Connection,
+    table_name: &str,
+) -> Result<Vec<ColumnInfo>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table_name))?;
+    let columns = stmt
+        .query_map([], |row| {
+            Ok(ColumnInfo {
+                name: row.get(1)?,
+                not_null: row.get(3)?,
+                primary_key: row.get::<_, i64>(5)? > 0,
+            })
+        })?
+        .collect::<Result<Vec<ColumnInfo>, _>>()?;
+
+    Ok(columns)
+}
+
+pub fn column_names(
+    conn: &Connection,
+    table_name: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    Ok(column_info(conn, table_name)?
+        .into_iter()
+        .map(|column| column.name)
+        .collect())
+}
+
+// Fetches the rowid and contents of every row matching the WHERE clause
+pub fn fetch_rows(
+    conn: &Connection,
+    table_name: &str,
+    where_str: &str,
+) -> Result<Vec<(i64, RowData)>, Box<dyn std::error::Error>> {
+    let columns = column_names(conn, table_name)?;
+    let sql = format!("SELECT rowid, * FROM {} WHERE {}", table_name, where_str);
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([])?;
+
+    let mut fetched = Vec::new();
+    while let Some(row) = rows.next()? {
+        let rowid: i64 = row.get(0)?;
+        let mut data = RowData::new();
+        for (i, column) in columns.iter().enumerate() {
+            data.insert(column.clone(), to_json(row.get(i + 1)?));
+        }
+        fetched.push((rowid, data));
+    }
+
+    Ok(fetched)
+}
+
+// Puts a row back, trusting that its columns still exist in the table
+pub fn insert_row(
+    conn: &Connection,
+    table_name: &str,
+    data: &RowData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let columns: Vec<&str> = data.keys().map(|column| column.as_str()).collect();
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table_name,
+        columns.join(", "),
+        placeholders
+    );
+
+    let values: Vec<Value> = data.values().map(from_json).collect();
+    conn.execute(&sql, rusqlite::params_from_iter(values))?;
+
+    Ok(())
+}
+
+// Moves a value over to the column's new name, if the row has that column at all
+pub fn rename_key(data: &mut RowData, from: &str, to: &str) {
+    if let Some(value) = data.remove(from) {
+        data.insert(to.to_string(), value);
+    }
+}
+
+pub fn to_json(value: Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(i) => serde_json::Value::from(i),
+        Value::Real(f) => Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Value::Text(s) => serde_json::Value::String(s),
+        Value::Blob(b) => serde_json::Value::from(b),
+    }
+}
+
+pub fn from_json(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        serde_json::Value::Array(items) => Value::Blob(
+            items
+                .iter()
+                .filter_map(|item| item.as_u64().map(|byte| byte as u8))
+                .collect(),
+        ),
+        serde_json::Value::Object(_) => Value::Text(value.to_string()),
+    }
+}
+
+// Every table the user made, leaving out WackyDB's own and SQLite's
+pub fn user_tables(conn: &Connection) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+
+    Ok(names
+        .into_iter()
+        .filter(|name| !crate::is_internal_table(name))
+        .collect())
+}
+
+pub fn has_table(conn: &Connection, table_name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(user_tables(conn)?.iter().any(|table| table == table_name))
+}
+
+// The preferred table if it exists, or any other user table at random if it doesn't (yet).
+// None if there are no user tables at all.
+pub fn pick_table<R: Rng>(
+    conn: &Connection,
+    rng: &mut R,
+    preferred: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let tables = user_tables(conn)?;
+    if tables.iter().any(|table| table == preferred) {
+        return Ok(Some(preferred.to_string()));
+    }
+
+    Ok(tables.choose(rng).cloned())
+}
+
+// Databases for tests to run against
+#[cfg(test)]
+pub mod fixtures {
+    use rusqlite::Connection;
+
+    use crate::bitrot;
+
+    // An in-memory database with all of WackyDB's own tables and a `pets` table
+    pub fn pets() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::ensure_internal_tables(&conn).unwrap();
+        add_pets(&conn);
+        conn
+    }
+
+    // rex, tom and kit, checksummed as if they'd been inserted like any other rows
+    pub fn add_pets(conn: &Connection) {
+        conn.execute_batch(
+            "CREATE TABLE pets (id INTEGER PRIMARY KEY, name TEXT);
+             INSERT INTO pets VALUES (1, 'rex'), (2, 'tom'), (3, 'kit');",
+        )
+        .unwrap();
+        bitrot::checksum_rows(conn, "pets", "1 = 1").unwrap();
+    }
+}
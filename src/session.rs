@@ -0,0 +1,495 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::chaos::ChaosProfile;
+use crate::game::{GameResult, Tape};
+use crate::leaderboard::DEFAULT_PLAYER;
+
+// Prints a line, and writes it down if a session is being recorded or replayed
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::session::print_line(&format!($($arg)*))
+    };
+}
+pub(crate) use say;
+
+// The first line of a session file: everything needed to start the database over
+#[derive(Serialize, Deserialize)]
+pub struct SessionHeader {
+    pub seed: u64,
+    pub difficulty: String,
+    pub chaos_profile: ChaosProfile,
+    // Whose name goes on the leaderboard, so a replay under another $USER prints the same
+    #[serde(default = "default_player")]
+    pub player: String,
+    // A copy of the database as it was when recording started
+    pub database: String,
+}
+
+fn default_player() -> String {
+    DEFAULT_PLAYER.to_string()
+}
+
+// One line typed at the prompt and everything that followed it
+#[derive(Serialize, Deserialize, Default)]
+pub struct Step {
+    pub input: String,
+    // The effect rolled for every statement, proceed_as_normal included
+    pub chaos: Vec<String>,
+    // How each game played went, and a tape of it for the replay to play it again
+    pub games: Vec<PlayedGame>,
+    pub output: String,
+}
+
+// A game as it went, along with why it never started if it didn't
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlayedGame {
+    #[serde(flatten)]
+    pub result: GameResult,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wouldnt_start: Option<String>,
+    // What the game was played with. Sessions recorded before these were kept have no tape,
+    // and their results stand in for the games.
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tape: Option<Tape>,
+}
+
+enum Mode {
+    Record(File),
+    Replay {
+        expected: VecDeque<Step>,
+        line: usize,
+        diverged: usize,
+    },
+}
+
+struct Session {
+    mode: Mode,
+    current: Option<Step>,
+}
+
+thread_local! {
+    static SESSION: RefCell<Option<Session>> = const { RefCell::new(None) };
+}
+
+pub fn print_line(text: &str) {
+    println!("{}", text);
+    with_step(|step| {
+        step.output.push_str(text);
+        step.output.push('\n');
+    });
+}
+
+// Where the copy of the database a session at `path` starts from is kept, right next to it
+pub fn database_copy(path: &str) -> String {
+    format!("{}.sqlite", path)
+}
+
+// Starts writing every step to the file. The database as it is now is copied to the header's
+// `database`, so the replay can start from the same data.
+pub fn record(
+    path: &str,
+    conn: &rusqlite::Connection,
+    header: SessionHeader,
+) -> Result<(), Box<dyn std::error::Error>> {
+    conn.backup(rusqlite::DatabaseName::Main, &header.database, None)?;
+
+    let mut file = File::create(path)?;
+    writeln!(file, "{}", serde_json::to_string(&header)?)?;
+
+    SESSION.with(|session| {
+        *session.borrow_mut() = Some(Session {
+            mode: Mode::Record(file),
+            current: None,
+        })
+    });
+
+    Ok(())
+}
+
+// Reads a session file back and gets ready to check every step against it. The recorded
+// database is copied to `database_path` for the replay to run on.
+pub fn replay(
+    path: &str,
+    database_path: &str,
+) -> Result<SessionHeader, Box<dyn std::error::Error>> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header: SessionHeader = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => return Err(format!("'{}' is an empty session", path).into()),
+    };
+    let expected = lines
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect::<Result<VecDeque<Step>, Box<dyn std::error::Error>>>()?;
+
+    fs::copy(&header.database, database_path)?;
+
+    SESSION.with(|session| {
+        *session.borrow_mut() = Some(Session {
+            mode: Mode::Replay {
+                expected,
+                line: 0,
+                diverged: 0,
+            },
+            current: None,
+        })
+    });
+
+    Ok(header)
+}
+
+// Whether a session is being recorded or replayed
+pub fn is_active() -> bool {
+    SESSION.with(|session| session.borrow().is_some())
+}
+
+// The next recorded line to feed in, if a replay is running. An empty string means the
+// recording has run out.
+pub fn next_replay_input() -> Option<String> {
+    SESSION.with(|session| match session.borrow().as_ref()?.mode {
+        Mode::Replay { ref expected, .. } => Some(
+            expected
+                .front()
+                .map(|step| step.input.clone())
+                .unwrap_or_default(),
+        ),
+        Mode::Record(_) => None,
+    })
+}
+
+pub fn begin_step(input: &str) {
+    SESSION.with(|session| {
+        if let Some(session) = session.borrow_mut().as_mut() {
+            session.current = Some(Step {
+                input: input.to_string(),
+                ..Step::default()
+            });
+        }
+    });
+}
+
+pub fn note_chaos(effect: &str) {
+    with_step(|step| step.chaos.push(effect.to_string()));
+}
+
+// Plays a game on `seed`, unless this is a replay, where the game is played again from its
+// tape without being shown, and how it went is checked against the recording along with the
+// rest of the step. A game that wouldn't start comes back as the reason why, recorded just
+// like a result.
+pub fn game_result(
+    seed: u64,
+    play: impl FnOnce() -> Result<(GameResult, Tape), String>,
+    replay: impl FnOnce(&Tape) -> Result<GameResult, String>,
+) -> Result<GameResult, String> {
+    let recorded = SESSION.with(|session| {
+        let mut session = session.borrow_mut();
+        let session = session.as_mut()?;
+        let Mode::Replay { expected, .. } = &session.mode else {
+            return None;
+        };
+        let played = session.current.as_ref().map_or(0, |step| step.games.len());
+        expected.front()?.games.get(played).cloned()
+    });
+
+    let played = match recorded {
+        Some(PlayedGame {
+            tape: Some(tape),
+            wouldnt_start: None,
+            ..
+        }) => played(seed, replay(&tape), Some(tape)),
+        // Nothing to play again, so the recording stands in
+        Some(recorded) => PlayedGame { seed, ..recorded },
+        None => match play() {
+            Ok((result, tape)) => played(seed, Ok(result), Some(tape)),
+            Err(reason) => played(seed, Err(reason), None),
+        },
+    };
+    with_step(|step| step.games.push(played.clone()));
+    match played.wouldnt_start {
+        Some(reason) => Err(reason),
+        None => Ok(played.result),
+    }
+}
+
+fn played(seed: u64, result: Result<GameResult, String>, tape: Option<Tape>) -> PlayedGame {
+    let (result, wouldnt_start) = match result {
+        Ok(result) => (result, None),
+        Err(reason) => (GameResult::forfeit(), Some(reason)),
+    };
+    PlayedGame {
+        result,
+        wouldnt_start,
+        seed,
+        tape,
+    }
+}
+
+// Writes the step down, or checks it against the recording
+pub fn end_step() -> Result<(), Box<dyn std::error::Error>> {
+    SESSION.with(|session| {
+        let mut session = session.borrow_mut();
+        let Some(session) = session.as_mut() else {
+            return Ok(());
+        };
+        let Some(step) = session.current.take() else {
+            return Ok(());
+        };
+
+        match &mut session.mode {
+            Mode::Record(file) => {
+                writeln!(file, "{}", serde_json::to_string(&step)?)?;
+            }
+            Mode::Replay {
+                expected,
+                line,
+                diverged,
+            } => {
+                *line += 1;
+                let Some(wanted) = expected.pop_front() else {
+                    return Ok(());
+                };
+                if let Some(difference) = compare(&wanted, &step) {
+                    *diverged += 1;
+                    println!(
+                        "⚠️  Replay diverged at line {} `{}`: {}",
+                        line,
+                        step.input.trim(),
+                        difference
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+// How the replay went: lines replayed and how many of them diverged
+pub fn finish() -> Option<(usize, usize)> {
+    SESSION.with(|session| match session.borrow_mut().take()?.mode {
+        Mode::Replay { line, diverged, .. } => Some((line, diverged)),
+        Mode::Record(_) => None,
+    })
+}
+
+fn with_step(f: impl FnOnce(&mut Step)) {
+    SESSION.with(|session| {
+        if let Some(step) = session
+            .borrow_mut()
+            .as_mut()
+            .and_then(|session| session.current.as_mut())
+        {
+            f(step);
+        }
+    });
+}
+
+fn compare(wanted: &Step, got: &Step) -> Option<String> {
+    if wanted.chaos != got.chaos {
+        return Some(format!(
+            "expected chaos {:?}, got {:?}",
+            wanted.chaos, got.chaos
+        ));
+    }
+    if wanted.games != got.games {
+        // The tapes are far too long to show, and the replay plays the recorded ones anyway
+        let games = |step: &Step| {
+            step.games
+                .iter()
+                .map(|game| (game.seed, game.result, game.wouldnt_start.clone()))
+                .collect::<Vec<_>>()
+        };
+        return Some(format!(
+            "expected games {:?}, got {:?}",
+            games(wanted),
+            games(got)
+        ));
+    }
+
+    let wanted_output = mask_timestamps(&wanted.output);
+    let got_output = mask_timestamps(&got.output);
+    wanted_output
+        .lines()
+        .zip(got_output.lines())
+        .find(|(wanted, got)| wanted != got)
+        .map(|(wanted, got)| format!("expected \"{}\", got \"{}\"", wanted, got))
+        .or_else(|| {
+            (wanted_output.lines().count() != got_output.lines().count()).then(|| {
+                format!(
+                    "expected {} line(s) of output, got {}",
+                    wanted_output.lines().count(),
+                    got_output.lines().count()
+                )
+            })
+        })
+}
+
+// Timestamps (like "2024-01-31 12:34:56") will never match between runs, so they're blanked
+fn mask_timestamps(text: &str) -> String {
+    const PATTERN: &[u8] = b"dddd-dd-dd dd:dd:dd";
+
+    let mut masked = text.to_string();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i + PATTERN.len() <= bytes.len() {
+        let is_timestamp = PATTERN
+            .iter()
+            .zip(&bytes[i..])
+            .all(|(pattern, byte)| match pattern {
+                b'd' => byte.is_ascii_digit(),
+                _ => pattern == byte,
+            });
+        if is_timestamp {
+            masked.replace_range(i..i + PATTERN.len(), "????-??-?? ??:??:??");
+            i += PATTERN.len();
+        } else {
+            i += 1;
+        }
+    }
+
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(chaos: &[&str], games: &[bool], output: &str) -> Step {
+        Step {
+            input: "SELECT * FROM pets\n".to_string(),
+            chaos: chaos.iter().map(|name| name.to_string()).collect(),
+            games: games
+                .iter()
+                .map(|won| PlayedGame {
+                    result: GameResult {
+                        won: *won,
+                        score: if *won { 10 } else { 0 },
+                        misses: 0,
+                        ..GameResult::forfeit()
+                    },
+                    wouldnt_start: None,
+                    seed: 1,
+                    tape: None,
+                })
+                .collect(),
+            output: output.to_string(),
+        }
+    }
+
+    #[test]
+    fn sessions_recorded_without_a_player_replay_anonymously() {
+        let header = SessionHeader {
+            seed: 1,
+            difficulty: "normal".to_string(),
+            chaos_profile: ChaosProfile::default(),
+            player: "ana".to_string(),
+            database: "session.sqlite".to_string(),
+        };
+        let mut json = serde_json::to_value(&header).unwrap();
+        assert_eq!(json["player"], "ana");
+
+        json.as_object_mut().unwrap().remove("player");
+        let header: SessionHeader = serde_json::from_value(json).unwrap();
+        assert_eq!(header.player, DEFAULT_PLAYER);
+    }
+
+    // Starts replaying the one step, then plays its game again with `replay`
+    fn replay_game(
+        recorded: PlayedGame,
+        replay: impl FnOnce(&Tape) -> Result<GameResult, String>,
+    ) -> (Result<GameResult, String>, Option<(usize, usize)>) {
+        let mut wanted = step(&[], &[], "");
+        wanted.games.push(recorded.clone());
+        SESSION.with(|session| {
+            *session.borrow_mut() = Some(Session {
+                mode: Mode::Replay {
+                    expected: VecDeque::from([wanted]),
+                    line: 0,
+                    diverged: 0,
+                },
+                current: None,
+            })
+        });
+
+        begin_step("SELECT * FROM pets\n");
+        let result = game_result(
+            recorded.seed,
+            || panic!("a replay never hands the terminal to a game"),
+            replay,
+        );
+        end_step().unwrap();
+        (result, finish())
+    }
+
+    #[test]
+    fn replays_play_the_game_again_from_its_tape() {
+        let mut recorded = step(&[], &[true], "").games.remove(0);
+        recorded.tape = Some(Tape::default());
+
+        let (result, replayed) = replay_game(recorded.clone(), |_| Ok(recorded.result));
+        assert_eq!(result, Ok(recorded.result));
+        assert_eq!(replayed, Some((1, 0)));
+
+        // The same keys losing this time is something to point out
+        let lost = GameResult::forfeit();
+        let (result, replayed) = replay_game(recorded.clone(), |_| Ok(lost));
+        assert_eq!(result, Ok(lost));
+        assert_eq!(replayed, Some((1, 1)));
+
+        // Without a tape, the recorded result stands in
+        recorded.tape = None;
+        let (result, replayed) = replay_game(recorded.clone(), |_| unreachable!());
+        assert_eq!(result, Ok(recorded.result));
+        assert_eq!(replayed, Some((1, 0)));
+    }
+
+    #[test]
+    fn tapes_survive_the_session_file() {
+        let mut played = step(&[], &[true], "").games.remove(0);
+        played.tape = Some(Tape {
+            size: (80, 24),
+            polls: vec![crate::game::Poll {
+                at: std::time::Duration::from_micros(1_501_700),
+                key: Some(crossterm::event::KeyCode::Left.into()),
+            }],
+        });
+
+        let json = serde_json::to_string(&played).unwrap();
+        assert_eq!(serde_json::from_str::<PlayedGame>(&json).unwrap(), played);
+    }
+
+    #[test]
+    fn timestamps_are_masked() {
+        assert_eq!(
+            mask_timestamps("#1 2024-01-31 12:34:56 fire, 2024-02-01 00:00:00"),
+            "#1 ????-??-?? ??:??:?? fire, ????-??-?? ??:??:??"
+        );
+        assert_eq!(mask_timestamps("2024-01-31"), "2024-01-31");
+    }
+
+    #[test]
+    fn identical_steps_match_whatever_the_time() {
+        let wanted = step(&["bit_rot"], &[true], "#1 2024-01-31 12:34:56\n");
+        let got = step(&["bit_rot"], &[true], "#1 2025-06-01 08:00:00\n");
+
+        assert_eq!(compare(&wanted, &got), None);
+    }
+
+    #[test]
+    fn differences_are_reported_chaos_first() {
+        let wanted = step(&["bit_rot"], &[true], "a\n");
+
+        let difference = compare(&wanted, &step(&["hide_rows"], &[false], "b\n")).unwrap();
+        assert!(difference.contains("chaos"));
+        let difference = compare(&wanted, &step(&["bit_rot"], &[false], "b\n")).unwrap();
+        assert!(difference.contains("games"));
+        let difference = compare(&wanted, &step(&["bit_rot"], &[true], "a\nb\n")).unwrap();
+        assert!(difference.contains("line(s) of output"));
+    }
+}
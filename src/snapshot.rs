@@ -0,0 +1,436 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use rusqlite::types::Value;
+use rusqlite::Connection;
+
+use crate::rows::{self, RowData};
+use crate::{bitrot, drift, fire, trash};
+
+// Only the most recent few chaos events can be undone
+pub const MAX_SNAPSHOTS: usize = 5;
+
+// Where the triggers write down every row the effect touched, the way it was before
+const LOG_TABLE: &str = "_wacky_undo_log";
+
+// Triggers on the table, and on each of WackyDB's own tables that keep something about it,
+// set to note down every write until the effect is finished. They're TEMP triggers, so only
+// this connection's writes set them off: whatever decay or the gremlin get up to meanwhile,
+// on connections of their own, is none of the effect's doing.
+pub struct Before {
+    triggers: Vec<String>,
+}
+
+// What destructive effects did to the database
+#[derive(Default)]
+pub struct Changes {
+    rows: Vec<Change>,
+    renames: Vec<Rename>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty() && self.renames.is_empty()
+    }
+
+    pub fn append(&mut self, mut other: Changes) {
+        self.rows.append(&mut other.rows);
+        self.renames.append(&mut other.renames);
+    }
+}
+
+// What one destructive effect did to the database, so it can be taken back without taking
+// anything that happened since along with it
+pub struct Snapshot {
+    pub effect: String,
+    pub statement: String,
+    pub table_name: String,
+    changes: Changes,
+}
+
+impl Snapshot {
+    pub fn new(effect: &str, statement: &str, table_name: &str, changes: Changes) -> Self {
+        Self {
+            effect: effect.to_string(),
+            statement: statement.to_string(),
+            table_name: table_name.to_string(),
+            changes,
+        }
+    }
+}
+
+// Row changes are kept under the names tables and columns had right after the effect
+enum Change {
+    Inserted {
+        table_name: String,
+        rowid: i64,
+    },
+    Deleted {
+        table_name: String,
+        rowid: i64,
+        row: RowData,
+    },
+    Updated {
+        table_name: String,
+        rowid: i64,
+        before: RowData,
+        after: RowData,
+    },
+}
+
+// A rename schema drift did. Only columns have a table_name.
+struct Rename {
+    table_name: Option<String>,
+    old_name: String,
+    new_name: String,
+}
+
+#[derive(Default)]
+pub struct SnapshotStack {
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl SnapshotStack {
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.snapshots.len() == MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        self.snapshots.pop_back()
+    }
+}
+
+// Starts writing down what happens to the table, right before the effect
+pub fn take(conn: &Connection, table_name: &str) -> Result<Before, Box<dyn std::error::Error>> {
+    conn.execute(
+        &format!(
+            "CREATE TEMP TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY,
+                table_name TEXT NOT NULL,
+                row_id INTEGER NOT NULL,
+                old_row TEXT
+            )",
+            LOG_TABLE
+        ),
+        [],
+    )?;
+    conn.execute(&format!("DELETE FROM {}", LOG_TABLE), [])?;
+
+    let mut tables = vec![
+        trash::TRASH_TABLE,
+        bitrot::CHECKSUM_TABLE,
+        fire::FIRE_TABLE,
+        drift::ALIAS_TABLE,
+    ];
+    // A table that doesn't exist (yet) has no rows to keep
+    if rows::has_table(conn, table_name)? {
+        tables.push(table_name);
+    }
+
+    let mut before = Before {
+        triggers: Vec::new(),
+    };
+    for (i, table) in tables.into_iter().enumerate() {
+        if let Err(e) = before.watch(conn, i, table) {
+            before.stop_watching(conn);
+            return Err(e);
+        }
+    }
+
+    Ok(before)
+}
+
+impl Before {
+    // Reads back what the effect did to the rows it touched, and stops writing anything down
+    pub fn finish(self, conn: &Connection) -> Result<Changes, Box<dyn std::error::Error>> {
+        let log = read_log(conn);
+        self.stop_watching(conn);
+        let mut touched = log?;
+
+        let alias_ids: Vec<String> = touched
+            .iter()
+            .filter(|(table, _, before)| table == drift::ALIAS_TABLE && before.is_none())
+            .map(|(_, rowid, _)| rowid.to_string())
+            .collect();
+        let aliases = rows::fetch_rows(
+            conn,
+            drift::ALIAS_TABLE,
+            &format!("rowid IN ({})", alias_ids.join(", ")),
+        )?;
+        let renames = renames(aliases);
+        for rename in &renames {
+            for (table, _, before) in &mut touched {
+                match &rename.table_name {
+                    None if *table == rename.old_name => *table = rename.new_name.clone(),
+                    Some(renamed) if table == renamed => {
+                        if let Some(row) = before {
+                            rows::rename_key(row, &rename.old_name, &rename.new_name);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut changes = Vec::new();
+        for (table_name, rowid, before) in touched {
+            let now = rows::fetch_rows(conn, &table_name, &format!("rowid = {}", rowid))?
+                .pop()
+                .map(|(_, row)| row);
+            changes.extend(match (before, now) {
+                (None, Some(_)) => Some(Change::Inserted { table_name, rowid }),
+                (Some(row), None) => Some(Change::Deleted {
+                    table_name,
+                    rowid,
+                    row,
+                }),
+                (Some(before), Some(after)) if before != after => Some(Change::Updated {
+                    table_name,
+                    rowid,
+                    before,
+                    after,
+                }),
+                _ => None,
+            });
+        }
+
+        Ok(Changes {
+            rows: changes,
+            renames,
+        })
+    }
+
+    // Has every insert, update and delete on the table write the row's rowid, and what it
+    // held before, to the log
+    fn watch(
+        &mut self,
+        conn: &Connection,
+        i: usize,
+        table_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let old_row = rows::column_names(conn, table_name)?
+            .iter()
+            .map(|column| format!("'{}', OLD.{}", column, column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let quoted = table_name.replace('\'', "''");
+
+        for (event, row_id, old_row) in [
+            ("INSERT", "NEW.rowid", "NULL".to_string()),
+            ("UPDATE", "OLD.rowid", format!("json_object({})", old_row)),
+            ("DELETE", "OLD.rowid", format!("json_object({})", old_row)),
+        ] {
+            let trigger = format!("_wacky_undo_{}_{}", i, event.to_ascii_lowercase());
+            conn.execute(&format!("DROP TRIGGER IF EXISTS temp.{}", trigger), [])?;
+            conn.execute(
+                &format!(
+                    "CREATE TEMP TRIGGER {} AFTER {} ON {} BEGIN
+                        INSERT INTO {} (table_name, row_id, old_row)
+                        VALUES ('{}', {}, {});
+                    END",
+                    trigger, event, table_name, LOG_TABLE, quoted, row_id, old_row
+                ),
+                [],
+            )?;
+            self.triggers.push(trigger);
+        }
+
+        Ok(())
+    }
+
+    fn stop_watching(&self, conn: &Connection) {
+        for trigger in &self.triggers {
+            let _ = conn.execute(&format!("DROP TRIGGER IF EXISTS temp.{}", trigger), []);
+        }
+        let _ = conn.execute(&format!("DELETE FROM {}", LOG_TABLE), []);
+    }
+}
+
+// Takes back what the effect did and nothing else. Rows written since win: a row the effect
+// deleted only comes back if nothing has taken its place, and a value it changed is only put
+// back if it hasn't been changed again. The journal and the leaderboard aren't touched, so
+// the chaos that was undone stays on the record, and so does the game that was lost.
+pub fn restore(conn: &Connection, snapshot: &Snapshot) -> Result<(), Box<dyn std::error::Error>> {
+    let tx = conn.unchecked_transaction()?;
+
+    for change in snapshot.changes.rows.iter().rev() {
+        match change {
+            Change::Inserted { table_name, rowid } => {
+                conn.execute(
+                    &format!("DELETE FROM {} WHERE rowid = ?1", table_name),
+                    [rowid],
+                )?;
+            }
+            Change::Deleted {
+                table_name,
+                rowid,
+                row,
+            } => {
+                let columns: Vec<&str> = row.keys().map(|column| column.as_str()).collect();
+                let placeholders = vec!["?"; columns.len() + 1].join(", ");
+                let mut values = vec![Value::Integer(*rowid)];
+                values.extend(row.values().map(rows::from_json));
+                conn.execute(
+                    &format!(
+                        "INSERT OR IGNORE INTO {} (rowid, {}) VALUES ({})",
+                        table_name,
+                        columns.join(", "),
+                        placeholders
+                    ),
+                    rusqlite::params_from_iter(values),
+                )?;
+            }
+            Change::Updated {
+                table_name,
+                rowid,
+                before,
+                after,
+            } => {
+                let where_str = format!("rowid = {}", rowid);
+                let Some((_, now)) = rows::fetch_rows(conn, table_name, &where_str)?.pop() else {
+                    continue;
+                };
+                for (column, value) in before {
+                    if after.get(column) == Some(value) || now.get(column) != after.get(column) {
+                        continue;
+                    }
+                    conn.execute(
+                        &format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", table_name, column),
+                        rusqlite::params![rows::from_json(value), rowid],
+                    )?;
+                }
+            }
+        }
+    }
+
+    for rename in snapshot.changes.renames.iter().rev() {
+        let sql = match &rename.table_name {
+            None => format!(
+                "ALTER TABLE {} RENAME TO {}",
+                rename.new_name, rename.old_name
+            ),
+            Some(table_name) => format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {}",
+                table_name, rename.new_name, rename.old_name
+            ),
+        };
+        conn.execute(&sql, [])?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+// Every row the effect touched, once, under the name its table had at the time: its rowid,
+// and what it held before the effect got to it (None if the effect put it there)
+type Touched = Vec<(String, i64, Option<RowData>)>;
+
+fn read_log(conn: &Connection) -> Result<Touched, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT table_name, row_id, old_row FROM {} ORDER BY id",
+        LOG_TABLE
+    ))?;
+    let mut log = stmt.query([])?;
+
+    let mut touched = Touched::new();
+    let mut seen = BTreeSet::new();
+    while let Some(row) = log.next()? {
+        let (table_name, rowid): (String, i64) = (row.get(0)?, row.get(1)?);
+        // The first write says how the row was before; any later ones are the effect's own
+        if !seen.insert((table_name.clone(), rowid)) {
+            continue;
+        }
+        let before = match row.get::<_, Option<String>>(2)? {
+            Some(old_row) => Some(serde_json::from_str(&old_row)?),
+            None => None,
+        };
+        touched.push((table_name, rowid, before));
+    }
+
+    Ok(touched)
+}
+
+// The renames schema drift wrote down in between, oldest first
+fn renames(aliases: Vec<(i64, RowData)>) -> Vec<Rename> {
+    let text = |row: &RowData, column: &str| {
+        row.get(column)
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+    };
+
+    aliases
+        .into_iter()
+        .filter_map(|(_, row)| {
+            Some(Rename {
+                table_name: text(&row, "table_name"),
+                old_name: text(&row, "old_name")?,
+                new_name: text(&row, "new_name")?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rows::fixtures;
+
+    #[test]
+    fn only_this_connections_writes_are_written_down() {
+        let path = std::env::temp_dir().join(format!("wacky_undo_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        crate::ensure_internal_tables(&conn).unwrap();
+        fixtures::add_pets(&conn);
+        let elsewhere = Connection::open(&path).unwrap();
+
+        let before = take(&conn, "pets").unwrap();
+        conn.execute("DELETE FROM pets WHERE id = 1", []).unwrap();
+        elsewhere
+            .execute("UPDATE pets SET name = 'tim' WHERE id = 2", [])
+            .unwrap();
+        let changes = before.finish(&conn).unwrap();
+
+        assert!(matches!(
+            changes.rows.as_slice(),
+            [Change::Deleted { rowid: 1, .. }]
+        ));
+        let triggers: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_temp_master WHERE type = 'trigger'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(
+            triggers, 0,
+            "nothing is written down once the effect is done"
+        );
+
+        drop((conn, elsewhere));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_row_touched_twice_keeps_how_it_was_at_first() {
+        let conn = fixtures::pets();
+
+        let before = take(&conn, "pets").unwrap();
+        conn.execute_batch(
+            "UPDATE pets SET name = 'tim' WHERE id = 2;
+             UPDATE pets SET name = 'tam' WHERE id = 2;
+             INSERT INTO pets VALUES (4, 'bo');
+             DELETE FROM pets WHERE id = 4;",
+        )
+        .unwrap();
+        let changes = before.finish(&conn).unwrap();
+
+        let [Change::Updated { before, after, .. }] = changes.rows.as_slice() else {
+            panic!("expected just the one update");
+        };
+        assert_eq!(before["name"], "tom");
+        assert_eq!(after["name"], "tam");
+    }
+}
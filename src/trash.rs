@@ -0,0 +1,180 @@
+use rusqlite::{params, Connection};
+
+use crate::difficulty::Difficulty;
+use crate::rows::{self, RowData};
+use crate::{bitrot, fire};
+
+pub const TRASH_TABLE: &str = "_wacky_trash";
+
+pub struct TrashItem {
+    pub id: i64,
+    pub source_table: String,
+    pub data: RowData,
+    pub trashed_at: String,
+}
+
+pub fn ensure_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY,
+                source_table TEXT NOT NULL,
+                row_data TEXT NOT NULL,
+                trashed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            TRASH_TABLE
+        ),
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn trash_row(
+    conn: &Connection,
+    source_table: &str,
+    data: &RowData,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (source_table, row_data) VALUES (?1, ?2)",
+            TRASH_TABLE
+        ),
+        params![source_table, serde_json::to_string(data)?],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+// Trashes values that were about to be inserted, matching them up with the table's columns.
+// Half a row can't be put back, so values that don't fit the columns stay out of the trash.
+pub fn trash_values(
+    conn: &Connection,
+    source_table: &str,
+    values: &[String],
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let columns = rows::column_names(conn, source_table)?;
+    if columns.len() != values.len() {
+        return Err(format!(
+            "'{}' has {} column(s) but {} value(s) were given.",
+            source_table,
+            columns.len(),
+            values.len()
+        )
+        .into());
+    }
+    let data: RowData = columns
+        .into_iter()
+        .zip(values.iter().map(|value| value.clone().into()))
+        .collect();
+
+    trash_row(conn, source_table, &data)
+}
+
+// Moves rows matching the WHERE clause out of their table and into the trash. Share is the
+// percentage of them that goes, rounded up so at least one row always does.
+pub fn trash_matching_rows(
+    conn: &Connection,
+    source_table: &str,
+    where_str: &str,
+    share: u32,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut doomed = rows::fetch_rows(conn, source_table, where_str)?;
+    let going = doomed.len() * share.min(100) as usize;
+    doomed.truncate(going.div_ceil(100));
+    for (rowid, data) in &doomed {
+        trash_row(conn, source_table, data)?;
+        conn.execute(
+            &format!("DELETE FROM {} WHERE rowid = ?1", source_table),
+            [rowid],
+        )?;
+        bitrot::forget_rows(conn, source_table, &[*rowid])?;
+    }
+
+    Ok(doomed.len())
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<TrashItem>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, source_table, row_data, trashed_at FROM {} ORDER BY id",
+        TRASH_TABLE
+    ))?;
+    let mut rows = stmt.query([])?;
+
+    let mut items = Vec::new();
+    while let Some(row) = rows.next()? {
+        let row_data: String = row.get(2)?;
+        items.push(TrashItem {
+            id: row.get(0)?,
+            source_table: row.get(1)?,
+            data: serde_json::from_str(&row_data)?,
+            trashed_at: row.get(3)?,
+        });
+    }
+
+    Ok(items)
+}
+
+// Puts an item back where it came from, unless that's on fire. Returns the table it went
+// back into.
+pub fn restore(
+    conn: &Connection,
+    id: i64,
+    difficulty: Difficulty,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let item = list(conn)?
+        .into_iter()
+        .find(|item| item.id == id)
+        .ok_or_else(|| format!("There's nothing with id {} in the trash.", id))?;
+
+    fire::guard_write_unless_boring(conn, &item.source_table, difficulty)?;
+
+    rows::insert_row(conn, &item.source_table, &item.data).map_err(|e| {
+        format!(
+            "Couldn't put item {} back into '{}': {}",
+            id, item.source_table, e
+        )
+    })?;
+    bitrot::checksum_row(conn, &item.source_table, conn.last_insert_rowid())?;
+    conn.execute(&format!("DELETE FROM {} WHERE id = ?1", TRASH_TABLE), [id])?;
+
+    Ok(item.source_table)
+}
+
+// Keeps trashed rows restorable after schema drift renamed their table
+pub fn rename_table(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "UPDATE {} SET source_table = ?2 WHERE source_table = ?1",
+            TRASH_TABLE
+        ),
+        [from, to],
+    )?;
+
+    Ok(())
+}
+
+// ...or one of its columns
+pub fn rename_column(
+    conn: &Connection,
+    table_name: &str,
+    from: &str,
+    to: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for mut item in list(conn)? {
+        if item.source_table != table_name {
+            continue;
+        }
+        rows::rename_key(&mut item.data, from, to);
+        conn.execute(
+            &format!("UPDATE {} SET row_data = ?1 WHERE id = ?2", TRASH_TABLE),
+            params![serde_json::to_string(&item.data)?, item.id],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn empty(conn: &Connection) -> Result<usize, Box<dyn std::error::Error>> {
+    Ok(conn.execute(&format!("DELETE FROM {}", TRASH_TABLE), [])?)
+}
@@ -0,0 +1,594 @@
+use std::io;
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rusqlite::Connection;
+
+use crate::difficulty::Difficulty;
+use crate::game::{
+    run_loop, Frame, GameContext, GameIo, GameLoop, GameResult, MiniGame, Progress, GAVE_UP,
+};
+use crate::rows;
+
+// How many questions a round has, if the database has that much to ask about
+const QUESTIONS: usize = 5;
+// How long there is to answer each one, on normal difficulty
+const TIME_PER_QUESTION: Duration = Duration::from_secs(20);
+// The countdown is redrawn at least this often
+const REDRAW_EVERY: Duration = Duration::from_secs(1);
+// Wrong answers offered next to the right one
+const WRONG_CHOICES: usize = 3;
+// Tries at coming up with questions before settling for fewer
+const ATTEMPTS: usize = 50;
+
+// Multiple choice questions about the user's own schema
+pub struct Trivia;
+
+impl MiniGame for Trivia {
+    fn name(&self) -> &str {
+        "trivia"
+    }
+
+    fn play(&self, io: &mut GameIo, ctx: &GameContext) -> io::Result<GameResult> {
+        let mut rng = StdRng::seed_from_u64(ctx.seed);
+        let questions =
+            questions(ctx.conn, &mut rng).map_err(|e| io::Error::other(e.to_string()))?;
+        // Less time to think the harder it gets
+        let time_per_question = TIME_PER_QUESTION * 100 / ctx.difficulty.severity().max(50);
+        let mut quiz = Quiz::new(questions, to_win(ctx.difficulty), time_per_question);
+
+        run_loop(&mut quiz, io)
+    }
+}
+
+// Right answers needed to win a full round
+fn to_win(difficulty: Difficulty) -> usize {
+    match difficulty {
+        Difficulty::Boring | Difficulty::Easy => 2,
+        Difficulty::Normal => 3,
+        Difficulty::Nightmare => 4,
+    }
+}
+
+#[derive(Debug)]
+struct Question {
+    prompt: String,
+    choices: Vec<String>,
+    // Which of the choices is right
+    answer: usize,
+}
+
+impl Question {
+    // Mixes the right answer in with the wrong ones
+    fn new(prompt: String, right: String, wrong: Vec<String>, rng: &mut StdRng) -> Self {
+        let mut choices = wrong;
+        choices.push(right.clone());
+        choices.shuffle(rng);
+        let answer = choices
+            .iter()
+            .position(|choice| *choice == right)
+            .expect("the right answer is one of the choices");
+
+        Self {
+            prompt,
+            choices,
+            answer,
+        }
+    }
+
+    fn right_answer(&self) -> &str {
+        &self.choices[self.answer]
+    }
+}
+
+// Up to QUESTIONS different questions about random user tables. Every answer comes straight
+// from sqlite_master or the table itself.
+fn questions(
+    conn: &Connection,
+    rng: &mut StdRng,
+) -> Result<Vec<Question>, Box<dyn std::error::Error>> {
+    let tables = rows::user_tables(conn)?;
+    let mut questions: Vec<Question> = Vec::new();
+
+    for _ in 0..ATTEMPTS {
+        if questions.len() == QUESTIONS {
+            break;
+        }
+        let Some(table_name) = tables.choose(rng) else {
+            break;
+        };
+        let question = match rng.gen_range(0..4) {
+            0 => row_count_question(conn, rng, table_name)?,
+            1 => column_count_question(conn, rng, table_name)?,
+            2 => primary_key_question(conn, rng, table_name)?,
+            _ => whose_column_question(conn, rng, &tables, table_name)?,
+        };
+        if let Some(question) = question {
+            if !questions
+                .iter()
+                .any(|asked| asked.prompt == question.prompt)
+            {
+                questions.push(question);
+            }
+        }
+    }
+
+    Ok(questions)
+}
+
+fn row_count_question(
+    conn: &Connection,
+    rng: &mut StdRng,
+    table_name: &str,
+) -> Result<Option<Question>, Box<dyn std::error::Error>> {
+    let count: i64 =
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table_name), [], |row| {
+            row.get(0)
+        })?;
+
+    Ok(Some(Question::new(
+        format!("How many rows does `{}` have?", table_name),
+        count.to_string(),
+        near_misses(count as usize, 0, rng),
+        rng,
+    )))
+}
+
+fn column_count_question(
+    conn: &Connection,
+    rng: &mut StdRng,
+    table_name: &str,
+) -> Result<Option<Question>, Box<dyn std::error::Error>> {
+    let count = rows::column_names(conn, table_name)?.len();
+
+    Ok(Some(Question::new(
+        format!("How many columns does `{}` have?", table_name),
+        count.to_string(),
+        near_misses(count, 1, rng),
+        rng,
+    )))
+}
+
+// Only asked of tables with a single column primary key and some other columns to pick from
+fn primary_key_question(
+    conn: &Connection,
+    rng: &mut StdRng,
+    table_name: &str,
+) -> Result<Option<Question>, Box<dyn std::error::Error>> {
+    let (keys, others): (Vec<_>, Vec<_>) = rows::column_info(conn, table_name)?
+        .into_iter()
+        .partition(|column| column.primary_key);
+    let [key] = keys.as_slice() else {
+        return Ok(None);
+    };
+    if others.is_empty() {
+        return Ok(None);
+    }
+
+    let wrong = others
+        .choose_multiple(rng, WRONG_CHOICES)
+        .map(|column| column.name.clone())
+        .collect();
+    Ok(Some(Question::new(
+        format!("Which column of `{}` is the PRIMARY KEY?", table_name),
+        key.name.clone(),
+        wrong,
+        rng,
+    )))
+}
+
+// Only asked about a column no other table has, when there are other tables to pick from
+fn whose_column_question(
+    conn: &Connection,
+    rng: &mut StdRng,
+    tables: &[String],
+    table_name: &str,
+) -> Result<Option<Question>, Box<dyn std::error::Error>> {
+    let mut others = Vec::new();
+    for other in tables.iter().filter(|other| *other != table_name) {
+        others.push((other.clone(), rows::column_names(conn, other)?));
+    }
+    if others.is_empty() {
+        return Ok(None);
+    }
+
+    let columns = rows::column_names(conn, table_name)?;
+    let unique: Vec<&String> = columns
+        .iter()
+        .filter(|column| {
+            !others.iter().any(|(_, other_columns)| {
+                other_columns
+                    .iter()
+                    .any(|other| other.eq_ignore_ascii_case(column))
+            })
+        })
+        .collect();
+    let Some(column) = unique.choose(rng) else {
+        return Ok(None);
+    };
+
+    let wrong = others
+        .choose_multiple(rng, WRONG_CHOICES)
+        .map(|(other, _)| other.clone())
+        .collect();
+    Ok(Some(Question::new(
+        format!("Which table has a column named `{}`?", column),
+        table_name.to_string(),
+        wrong,
+        rng,
+    )))
+}
+
+// Wrong numbers close enough to the right one to make you think twice
+fn near_misses(right: usize, min: usize, rng: &mut StdRng) -> Vec<String> {
+    let mut candidates: Vec<usize> = (right.saturating_sub(5)..=right + 5)
+        .chain([right / 2, right * 2, right + 10])
+        .filter(|&n| n >= min && n != right)
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    candidates
+        .choose_multiple(rng, WRONG_CHOICES)
+        .map(|n| n.to_string())
+        .collect()
+}
+
+struct Quiz {
+    questions: Vec<Question>,
+    // The question being asked, which is past the end once they've all been asked
+    current: usize,
+    score: u32,
+    misses: u32,
+    to_win: usize,
+    time_per_question: Duration,
+    // Time left on the current question, as of the last check
+    time_left: Duration,
+    // The question the clock is running for, and when, since the start, it was asked
+    timed: usize,
+    asked_at: Duration,
+    // How the last answer went
+    feedback: Option<String>,
+    progress: Progress,
+}
+
+impl Quiz {
+    fn new(questions: Vec<Question>, to_win: usize, time_per_question: Duration) -> Self {
+        Self {
+            // A short round can't ask for more than it has
+            to_win: to_win.min(questions.len()),
+            progress: Progress {
+                over: questions.is_empty(),
+                ..Progress::default()
+            },
+            questions,
+            current: 0,
+            score: 0,
+            misses: 0,
+            time_per_question,
+            time_left: time_per_question,
+            timed: 0,
+            asked_at: Duration::ZERO,
+            feedback: None,
+        }
+    }
+
+    // None is for running out of time
+    fn answer(&mut self, choice: Option<usize>) {
+        let question = &self.questions[self.current];
+        self.feedback = Some(match choice {
+            Some(choice) if choice == question.answer => {
+                self.score += 1;
+                format!("Right! It's {}.", question.right_answer())
+            }
+            Some(_) => {
+                self.misses += 1;
+                format!("Nope, it was {}.", question.right_answer())
+            }
+            None => {
+                self.misses += 1;
+                format!("Time's up! It was {}.", question.right_answer())
+            }
+        });
+
+        self.current += 1;
+        if self.current == self.questions.len() {
+            self.progress.over = true;
+        }
+    }
+
+    fn won(&self) -> bool {
+        !self.progress.forfeited && self.score as usize >= self.to_win
+    }
+}
+
+impl GameLoop for Quiz {
+    fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    fn progress_mut(&mut self) -> &mut Progress {
+        &mut self.progress
+    }
+
+    // The seconds left are redrawn as they tick down
+    fn wait(&self, _elapsed: Duration) -> Duration {
+        self.time_left.min(REDRAW_EVERY)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let KeyCode::Char(c) = key.code else {
+            return;
+        };
+        let choices = self.questions[self.current].choices.len();
+        match c.to_digit(10).map(|digit| digit as usize) {
+            Some(choice) if (1..=choices).contains(&choice) => self.answer(Some(choice - 1)),
+            _ => {}
+        }
+    }
+
+    // Giving up counts as getting every question left wrong, so it's never cheaper than
+    // guessing
+    fn charge_forfeit(&mut self) {
+        self.misses += (self.questions.len() - self.current) as u32;
+    }
+
+    // Runs the clock down on the question being asked, and starts it over on the next one
+    fn advance(&mut self, elapsed: Duration) {
+        if self.current == self.timed {
+            self.time_left = self
+                .time_per_question
+                .saturating_sub(elapsed - self.asked_at);
+            if self.time_left.is_zero() {
+                self.answer(None);
+            }
+        }
+        if self.current != self.timed {
+            self.timed = self.current;
+            self.asked_at = elapsed;
+            self.time_left = self.time_per_question;
+        }
+    }
+
+    fn frame(&self) -> Frame {
+        let mut frame = Frame::default();
+        let number = (self.current + 1).min(self.questions.len());
+        let choices = self
+            .questions
+            .get(self.current)
+            .map_or(WRONG_CHOICES + 1, |question| question.choices.len());
+        frame.put(
+            0,
+            0,
+            &format!(
+                "Question {}/{}  Right {}/{}  Wrong {}  {}s left  (1-{} to answer, q to give up)",
+                number,
+                self.questions.len(),
+                self.score,
+                self.to_win,
+                self.misses,
+                self.time_left.as_secs(),
+                choices
+            ),
+        );
+
+        let mut y = 2;
+        if let Some(question) = self
+            .questions
+            .get(self.current)
+            .filter(|_| !self.progress.over)
+        {
+            frame.put(0, y, &question.prompt);
+            y += 2;
+            for (i, choice) in question.choices.iter().enumerate() {
+                frame.put(2, y, &format!("{}) {}", i + 1, choice));
+                y += 1;
+            }
+            y += 1;
+        }
+        if let Some(feedback) = &self.feedback {
+            frame.put(0, y, feedback);
+            y += 2;
+        }
+
+        if self.progress.over {
+            let message = if self.questions.is_empty() {
+                "There's nothing in here to quiz you on. Free pass!"
+            } else if self.progress.forfeited {
+                GAVE_UP
+            } else if self.won() {
+                "You Won! You know your schema."
+            } else {
+                "Game Over! Time to get to know your schema."
+            };
+            frame.put(0, y, message);
+        }
+
+        frame
+    }
+
+    fn result(&self) -> GameResult {
+        GameResult {
+            won: self.won(),
+            score: self.score,
+            misses: self.misses,
+            duration: self.progress.elapsed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chaos::{PendingOperation, StatementKind};
+    use crate::game::headless::{self, BufferRenderer, ManualClock, ScriptedInput};
+    use crate::rows::fixtures;
+
+    // The usual pets, with a species each, and someone to keep them
+    fn zoo() -> Connection {
+        let conn = fixtures::pets();
+        conn.execute_batch(
+            "ALTER TABLE pets ADD COLUMN species TEXT;
+             UPDATE pets SET species = CASE name WHEN 'rex' THEN 'dog' ELSE 'cat' END;
+             CREATE TABLE keepers (id INTEGER PRIMARY KEY, name TEXT);
+             INSERT INTO keepers VALUES (7, 'ana');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn ctx<'a>(
+        conn: &'a Connection,
+        operation: &'a PendingOperation,
+        difficulty: Difficulty,
+    ) -> GameContext<'a> {
+        GameContext {
+            conn,
+            seed: 7,
+            difficulty,
+            operation,
+        }
+    }
+
+    fn select() -> PendingOperation {
+        PendingOperation::new(StatementKind::Select, "pets")
+    }
+
+    // Pressing the key for every answer, a second apart
+    fn keys_for(questions: &[Question], right: bool) -> Vec<(u64, KeyCode)> {
+        questions
+            .iter()
+            .enumerate()
+            .map(|(i, question)| {
+                let choice = if right {
+                    question.answer
+                } else {
+                    (question.answer + 1) % question.choices.len()
+                };
+                let key = char::from_digit(choice as u32 + 1, 10).unwrap();
+                (1000 * (i as u64 + 1), KeyCode::Char(key))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn answers_come_from_the_database() {
+        let conn = zoo();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let rows = row_count_question(&conn, &mut rng, "pets")
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows.right_answer(), "3");
+        let columns = column_count_question(&conn, &mut rng, "pets")
+            .unwrap()
+            .unwrap();
+        assert_eq!(columns.right_answer(), "3");
+        let key = primary_key_question(&conn, &mut rng, "pets")
+            .unwrap()
+            .unwrap();
+        assert_eq!(key.right_answer(), "id");
+        let whose =
+            whose_column_question(&conn, &mut rng, &["keepers".into(), "pets".into()], "pets")
+                .unwrap()
+                .unwrap();
+        assert_eq!(whose.prompt, "Which table has a column named `species`?");
+        assert_eq!(whose.right_answer(), "pets");
+    }
+
+    #[test]
+    fn every_choice_is_different() {
+        let conn = zoo();
+        let questions = questions(&conn, &mut StdRng::seed_from_u64(3)).unwrap();
+
+        assert!(!questions.is_empty());
+        for question in &questions {
+            let mut choices = question.choices.clone();
+            choices.sort();
+            choices.dedup();
+            assert_eq!(choices.len(), question.choices.len(), "{:?}", question);
+            assert!(choices.len() >= 2);
+        }
+    }
+
+    #[test]
+    fn knowing_your_schema_wins() {
+        let conn = zoo();
+        let operation = select();
+        let ctx = ctx(&conn, &operation, Difficulty::Nightmare);
+        let asked = questions(&conn, &mut StdRng::seed_from_u64(ctx.seed)).unwrap();
+
+        let (result, frames) = headless::play(&Trivia, &ctx, keys_for(&asked, true));
+        assert!(result.won);
+        assert_eq!(result.score as usize, asked.len());
+        assert!(frames.last().unwrap().ends_with("You know your schema."));
+    }
+
+    #[test]
+    fn wrong_answers_lose() {
+        let conn = zoo();
+        let operation = select();
+        let ctx = ctx(&conn, &operation, Difficulty::Easy);
+        let asked = questions(&conn, &mut StdRng::seed_from_u64(ctx.seed)).unwrap();
+
+        let (result, _) = headless::play(&Trivia, &ctx, keys_for(&asked, false));
+        assert!(!result.won);
+        assert_eq!(result.misses as usize, asked.len());
+    }
+
+    #[test]
+    fn running_out_of_time_is_a_wrong_answer() {
+        let conn = zoo();
+        let questions = vec![
+            row_count_question(&conn, &mut StdRng::seed_from_u64(1), "pets")
+                .unwrap()
+                .unwrap(),
+        ];
+        let mut quiz = Quiz::new(questions, 1, Duration::from_secs(5));
+
+        let clock = ManualClock::default();
+        let mut input = ScriptedInput::new(&clock, []);
+        let mut renderer = BufferRenderer::default();
+        let mut io = GameIo {
+            input: &mut input,
+            clock: &clock,
+            renderer: &mut renderer,
+        };
+        let result = run_loop(&mut quiz, &mut io).unwrap();
+
+        assert_eq!(result.misses, 1);
+        assert_eq!(result.duration, Duration::from_secs(5));
+        assert!(renderer
+            .frames
+            .last()
+            .unwrap()
+            .contains("Time's up! It was 3."));
+    }
+
+    #[test]
+    fn giving_up_gets_the_rest_wrong() {
+        let conn = zoo();
+        let operation = select();
+        let ctx = ctx(&conn, &operation, Difficulty::Normal);
+        let asked = questions(&conn, &mut StdRng::seed_from_u64(ctx.seed)).unwrap();
+        let mut keys = keys_for(&asked[..1], true);
+        keys.push((1500, KeyCode::Char('q')));
+
+        let (result, _) = headless::play(&Trivia, &ctx, keys);
+        assert!(!result.won);
+        assert_eq!(result.score, 1);
+        assert_eq!(result.misses as usize, asked.len() - 1);
+    }
+
+    #[test]
+    fn an_empty_database_is_a_free_pass() {
+        let conn = Connection::open_in_memory().unwrap();
+        let operation = select();
+        let (result, frames) =
+            headless::play(&Trivia, &ctx(&conn, &operation, Difficulty::Normal), []);
+
+        assert!(result.won);
+        assert!(frames.last().unwrap().ends_with("Free pass!"));
+    }
+}